@@ -0,0 +1,637 @@
+//! The home screen application, a window and its event loop
+use crate::config::Config;
+use crate::event::{
+    BackgroundImageLoadEvent, ContentSetLoadEvent, ContentSetPageLoadEvent, DetailsImageLoadEvent,
+    HeroImageLoadEvent, ImageLoadBatchEvent, TileRef,
+};
+use crate::input::{Action, Direction, Pointer, PointerTracker};
+use crate::model::home::ContentSetItem;
+use crate::service::cache::ImageCache;
+use crate::service::disney::DisneyService;
+use crate::service::image_queue::{ImageQueue, TileImageRequest};
+use crate::tasks::Tasks;
+use crate::ui::animation::Timing;
+use crate::ui::details_page::DetailsPage;
+use crate::ui::home_page::HomePage;
+use crate::ui::layout::Layout;
+use crate::ui::render;
+use crate::ui::shelf::PageRequest;
+use crate::ui::texture_cache::TextureCache;
+use anyhow::{Error, Result};
+use bytes::Bytes;
+use futures::StreamExt;
+use log::{debug, error, info, warn};
+use sdl2::controller::GameController;
+use sdl2::event::{Event, EventSender, WindowEvent};
+use sdl2::image::InitFlag;
+use sdl2::pixels::Color;
+use sdl2::render::Canvas;
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The longest quitting waits for background loading to stop,
+/// loading stops at its next await so this is rarely reached
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Runs the application until the window is closed, or ESC is pressed
+///
+/// Content is loaded in the background while the event loop runs,
+/// this must be called from within a tokio runtime
+pub async fn run(config: Config) -> Result<()> {
+    // the content source can be switched to local fixtures for running offline
+    // e.g. --content-source dir:./assets
+    let mut disney = DisneyService::new(config.concurrency, config.content_source()?)
+        .with_retry_policy(config.retry_policy())?;
+
+    // images are cached on disk across launches, a broken cache only costs us downloads
+    if config.image_cache_max_bytes > 0 {
+        match ImageCache::open(&config.image_cache_dir, config.image_cache_max_bytes) {
+            Ok(image_cache) => disney = disney.with_image_cache(image_cache),
+            Err(e) => warn!("Image cache disabled: {:?}", e),
+        }
+    }
+
+    let disney = Arc::new(disney);
+
+    info!("Starting up...");
+
+    // Load the SDL context
+    let sdl_context = sdl2::init().map_err(Error::msg)?;
+    let video_subsystem = sdl_context.video().map_err(Error::msg)?;
+    let _image_context = sdl2::image::init(InitFlag::PNG | InitFlag::JPG).map_err(Error::msg)?;
+    let ttf_context = sdl2::ttf::init().map_err(Error::msg)?;
+
+    let window = video_subsystem
+        .window("Home", config.window_width, config.window_height)
+        .position_centered()
+        .resizable()
+        .allow_highdpi()
+        .opengl()
+        .build()?;
+
+    // presenting waits for the display to refresh, which paces
+    // the frames while anything is animating
+    let mut canvas = window.into_canvas().present_vsync().build()?;
+
+    // sizes follow the drawable size of the window, in pixels,
+    // which is larger than the window size on high dpi displays
+    let (width, height) = canvas.output_size().map_err(Error::msg)?;
+    let mut layout = Layout::new(&config, width, height);
+
+    let mut font = ttf_context
+        .load_font(&config.font_path, layout.font_size)
+        .map_err(Error::msg)?;
+
+    // the home page starts empty, shelves are added
+    // as each content set is streamed in
+    let mut ui = HomePage::load(Vec::new(), layout, &config.image_size, Timing::new(&config));
+
+    // decoded images and rendered text are kept between frames
+    let texture_creator = canvas.texture_creator();
+    let mut textures = TextureCache::new(&texture_creator, config.texture_max_bytes);
+
+    // Draw the initial UI (it will be blank mostly until we have images)
+    update_ui(&mut canvas, &font, &mut textures, &mut ui, None);
+
+    // keys and controller buttons are mapped to actions, controllers
+    // are opened as they connect, and closed when dropped
+    let bindings = config.bindings()?;
+    let controller_subsystem = sdl_context.game_controller().map_err(Error::msg)?;
+    let mut controllers: Vec<GameController> = Vec::new();
+
+    // Initialize the event loop
+    let mut event_pump = sdl_context.event_pump().map_err(Error::msg)?;
+    let ev = sdl_context.event().map_err(Error::msg)?;
+    ev.register_custom_event::<ContentSetLoadEvent>()
+        .map_err(Error::msg)?;
+    ev.register_custom_event::<ContentSetPageLoadEvent>()
+        .map_err(Error::msg)?;
+    ev.register_custom_event::<ImageLoadBatchEvent>()
+        .map_err(Error::msg)?;
+    ev.register_custom_event::<DetailsImageLoadEvent>()
+        .map_err(Error::msg)?;
+    ev.register_custom_event::<HeroImageLoadEvent>()
+        .map_err(Error::msg)?;
+    ev.register_custom_event::<BackgroundImageLoadEvent>()
+        .map_err(Error::msg)?;
+
+    // kick off background process to async load the home screen and images and send events
+    // each content set and batch of images are sent to the main event loop
+    // to update the view. Tile images are queued as content sets load, and
+    // loaded most important first for what is on screen, see ImageQueue
+    //
+    // the home screen's loading runs until we quit, the details
+    // page's is cancelled when the page is closed
+    let mut tasks = Tasks::new();
+    let mut details_tasks = Tasks::new();
+    let image_queue = Arc::new(ImageQueue::default());
+    background_load_tile_images(
+        &mut tasks,
+        Arc::clone(&image_queue),
+        Arc::clone(&disney),
+        ev.event_sender(),
+    );
+    background_load(
+        &mut tasks,
+        Arc::clone(&disney),
+        Arc::clone(&image_queue),
+        config.image_size.clone(),
+        ev.event_sender(),
+    );
+
+    // This moves all the things we just drew to the foreground
+    canvas.present();
+
+    // the details page, when open, is shown instead of the home page
+    let mut details: Option<DetailsPage> = None;
+    let mut pointer = PointerTracker::new(canvas.window().size(), (width, height));
+    let mut last_frame = Instant::now();
+
+    'running: loop {
+        // render every frame while anything is moving, otherwise sleep
+        // until the next event, or the hero is due to move on
+        let animating = ui.is_animating() && details.is_none();
+        let events: Vec<Event> = if animating {
+            event_pump.poll_iter().collect()
+        } else {
+            let first = match ui.time_to_next_tick().filter(|_| details.is_none()) {
+                Some(seconds) => event_pump.wait_event_timeout((seconds * 1000.0).ceil() as u32),
+                None => Some(event_pump.wait_event()),
+            };
+            first.into_iter().chain(event_pump.poll_iter()).collect()
+        };
+        let mut redraw = false;
+
+        // Handle events forever
+        for event in events {
+            // the mouse, trackpads and touch screens point at tiles
+            if let Some(gesture) = pointer.on_event(&event) {
+                if details.is_some() {
+                    continue;
+                }
+                let focus = ui.focus();
+                let page_request = match gesture {
+                    Pointer::Hover { x, y } => ui.on_hover(x, y),
+                    Pointer::Tap { x, y } => {
+                        let page_request = ui.on_hover(x, y);
+                        if ui.tile_at(x, y).is_some() || ui.hero_at(x, y) {
+                            details = ui.selected_item().map(|item| {
+                                open_details(
+                                    item,
+                                    layout.shelf_padding,
+                                    &disney,
+                                    &mut details_tasks,
+                                    ev.event_sender(),
+                                )
+                            });
+                        }
+                        page_request
+                    }
+                    Pointer::Wheel { y, dx, dy, .. } => {
+                        ui.on_wheel(y, dx, dy);
+                        None
+                    }
+                    Pointer::Drag { y, dx, dy, .. } => ui.on_drag(y, dx, dy),
+                    Pointer::Fling { y, velocity_x, .. } => {
+                        ui.on_fling(y, velocity_x);
+                        None
+                    }
+                };
+                if let Some(page_request) = page_request {
+                    background_load_page(
+                        &mut tasks,
+                        page_request,
+                        Arc::clone(&disney),
+                        Arc::clone(&image_queue),
+                        config.image_size.clone(),
+                        ev.event_sender(),
+                    );
+                }
+                // moving the mouse only redraws when it moves onto another tile
+                redraw |= !matches!(gesture, Pointer::Hover { .. }) || ui.focus() != focus;
+                continue;
+            }
+
+            match event {
+                Event::Quit { .. } => {
+                    break 'running;
+                }
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(..),
+                    ..
+                } => {
+                    let (width, height) = canvas.output_size().map_err(Error::msg)?;
+                    let resized = Layout::new(&config, width, height);
+                    if resized == layout {
+                        continue;
+                    }
+                    info!("Resized to {}x{}", width, height);
+                    if resized.font_size != layout.font_size {
+                        font = ttf_context
+                            .load_font(&config.font_path, resized.font_size)
+                            .map_err(Error::msg)?;
+                        textures.clear_text();
+                    }
+                    layout = resized;
+                    pointer.resize(canvas.window().size(), (width, height));
+                    ui.on_resize(layout);
+                    if let Some(page) = details.as_mut() {
+                        page.on_resize(layout.shelf_padding);
+                    }
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    // sent for each controller connected at startup too
+                    match controller_subsystem.open(which) {
+                        Ok(controller) => {
+                            info!("Connected controller {:?}", controller.name());
+                            controllers.push(controller);
+                        }
+                        Err(e) => warn!("Unable to open controller {}: {:?}", which, e),
+                    }
+                    continue;
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    controllers.retain(|controller| controller.instance_id() != which);
+                    continue;
+                }
+                custom_event if custom_event.is_user_event() => {
+                    if let Some(ce) = custom_event.as_user_event_type::<ContentSetLoadEvent>() {
+                        let hero_image_urls = ui.add_shelf(ce.content_set);
+                        background_load_hero_images(
+                            &mut tasks,
+                            hero_image_urls,
+                            Arc::clone(&disney),
+                            ev.event_sender(),
+                        );
+                    } else if let Some(ce) =
+                        custom_event.as_user_event_type::<ContentSetPageLoadEvent>()
+                    {
+                        ui.on_page_load(ce.shelf_index, ce.content_set);
+                    } else if let Some(ce) =
+                        custom_event.as_user_event_type::<DetailsImageLoadEvent>()
+                    {
+                        if let Some(page) = details.as_mut() {
+                            page.on_image_load(ce);
+                        }
+                    } else if let Some(ce) = custom_event.as_user_event_type::<HeroImageLoadEvent>()
+                    {
+                        ui.on_hero_image_load(ce);
+                    } else if let Some(ce) =
+                        custom_event.as_user_event_type::<BackgroundImageLoadEvent>()
+                    {
+                        ui.on_background_image_load(ce);
+                    } else if let Some(ce) =
+                        custom_event.as_user_event_type::<ImageLoadBatchEvent>()
+                    {
+                        for event in ce.events {
+                            ui.on_image_load(event);
+                        }
+                    } else {
+                        warn!("Unknown custom event {:?}", custom_event);
+                        continue;
+                    }
+                }
+                input => match bindings.action(&input) {
+                    Some(Action::Back | Action::Quit) if details.is_some() => {
+                        // back to the home page, the details page stops loading
+                        details = None;
+                        details_tasks.cancel();
+                    }
+                    Some(action @ (Action::Back | Action::Quit)) => {
+                        // back goes to the top of the page, only quit leaves
+                        // from there, so a remote or controller can't close it
+                        if !ui.on_back() && action == Action::Quit {
+                            break 'running;
+                        }
+                    }
+                    Some(Action::Select) if details.is_none() => {
+                        details = ui.selected_item().map(|item| {
+                            open_details(
+                                item,
+                                layout.shelf_padding,
+                                &disney,
+                                &mut details_tasks,
+                                ev.event_sender(),
+                            )
+                        });
+                    }
+                    Some(action) if details.is_none() => {
+                        let page_request = match action {
+                            Action::Navigate(Direction::Left) => {
+                                ui.on_key_left();
+                                None
+                            }
+                            Action::Navigate(Direction::Right) => ui.on_key_right(),
+                            Action::Navigate(Direction::Down) => {
+                                ui.on_key_down();
+                                None
+                            }
+                            Action::Navigate(Direction::Up) => {
+                                ui.on_key_up();
+                                None
+                            }
+                            Action::PageLeft => {
+                                ui.on_page_left();
+                                None
+                            }
+                            Action::PageRight => ui.on_page_right(),
+                            Action::Select | Action::Back | Action::Quit => None,
+                        };
+                        if let Some(page_request) = page_request {
+                            background_load_page(
+                                &mut tasks,
+                                page_request,
+                                Arc::clone(&disney),
+                                Arc::clone(&image_queue),
+                                config.image_size.clone(),
+                                ev.event_sender(),
+                            );
+                        }
+                    }
+                    _ => continue,
+                },
+            }
+            redraw = true;
+        }
+
+        // animations and the hero move on by the time since the last frame,
+        // an animation started by this frame's events starts from here
+        let now = Instant::now();
+        let seconds = now.duration_since(last_frame).as_secs_f32();
+        last_frame = now;
+        if details.is_none() {
+            image_queue.set_viewport(ui.viewport());
+            // the background follows the focus, loading the art around it
+            background_load_images(
+                &mut tasks,
+                ui.take_image_requests(),
+                Arc::clone(&disney),
+                ev.event_sender(),
+                |img_url, bytes| BackgroundImageLoadEvent { img_url, bytes },
+            );
+            for page_request in ui.tick(seconds) {
+                background_load_page(
+                    &mut tasks,
+                    page_request,
+                    Arc::clone(&disney),
+                    Arc::clone(&image_queue),
+                    config.image_size.clone(),
+                    ev.event_sender(),
+                );
+            }
+        }
+        if redraw || animating || ui.is_animating() {
+            update_ui(&mut canvas, &font, &mut textures, &mut ui, details.as_mut());
+        }
+    }
+
+    // stop loading, anything still going after the timeout is left to the runtime
+    details_tasks.cancel();
+    if !tasks.shutdown(SHUTDOWN_TIMEOUT).await {
+        warn!(
+            "Background loading did not stop within {:?}",
+            SHUTDOWN_TIMEOUT
+        );
+    }
+    info!(
+        "Shut down, broken images and text = {}, failed draws = {}",
+        textures.failures(),
+        render::failures()
+    );
+    Ok(())
+}
+
+/// Opens the details page for an item, loading its images in the background
+fn open_details(
+    item: &ContentSetItem,
+    padding: u32,
+    disney: &Arc<DisneyService>,
+    tasks: &mut Tasks,
+    event_sender: EventSender,
+) -> DetailsPage {
+    let page = DetailsPage::load(item.clone(), padding);
+    background_load_images(
+        tasks,
+        page.image_urls(),
+        Arc::clone(disney),
+        event_sender,
+        |img_url, bytes| DetailsImageLoadEvent { img_url, bytes },
+    );
+    page
+}
+
+/// Actually paints the screen
+fn update_ui(
+    canvas: &mut Canvas<Window>,
+    font: &Font,
+    textures: &mut TextureCache<Window>,
+    ui: &mut HomePage,
+    details: Option<&mut DetailsPage>,
+) {
+    textures.begin_frame();
+    canvas.set_draw_color(Color::BLACK);
+    canvas.clear();
+    if let Some(details) = details {
+        details.draw(font, canvas, textures);
+    } else {
+        ui.draw(font, canvas, textures);
+    }
+    canvas.present();
+    textures.end_frame();
+}
+
+/// Background loads the home screen content sets
+/// and their tile images, piping them through
+/// to the evnet loop via the EventSender
+///
+/// Tile images for a content set start loading as soon
+/// as that content set arrives
+fn background_load(
+    tasks: &mut Tasks,
+    disney: Arc<DisneyService>,
+    image_queue: Arc<ImageQueue>,
+    image_size: String,
+    event_sender: EventSender,
+) {
+    tasks.spawn({
+        async move {
+            let start_time = Instant::now();
+            info!("Loading home contents...");
+            let content_sets = match disney.stream_home_content_sets().await {
+                Ok(content_sets) => content_sets,
+                Err(e) => {
+                    error!("Failure loading home screen: {:?}", e);
+                    return;
+                }
+            };
+
+            // Sends each content set into the main event loop
+            // before queueing any of its images, each set is
+            // added as the next shelf, see HomePage::add_shelf
+            let content_sets = content_sets
+                .filter_map(|result| async move {
+                    result
+                        .map_err(|e| error!("Failure loading content set: {:?}", e))
+                        .ok()
+                })
+                .enumerate();
+            futures::pin_mut!(content_sets);
+            while let Some((shelf, content_set)) = content_sets.next().await {
+                let first = TileRef { shelf, tile: 0 };
+                let requests = TileImageRequest::for_content_set(first, &content_set, &image_size);
+                if !push_event(&event_sender, ContentSetLoadEvent { content_set }) {
+                    return;
+                }
+                image_queue.push(requests);
+            }
+
+            let retry_stats = disney.retry_stats();
+            info!(
+                "Finished loading home contents duration = {} millis, requests = {}, retries = {}, failures = {}",
+                start_time.elapsed().as_millis(),
+                retry_stats.requests(),
+                retry_stats.retries(),
+                retry_stats.failures()
+            );
+        }
+    });
+}
+
+/// Loads tile images from the queue for as long as the app runs,
+/// sending them into the main event loop in batches
+fn background_load_tile_images(
+    tasks: &mut Tasks,
+    image_queue: Arc<ImageQueue>,
+    disney: Arc<DisneyService>,
+    event_sender: EventSender,
+) {
+    tasks.spawn(async move {
+        let start_time = Instant::now();
+        let images = disney.stream_queued_images(&image_queue);
+        futures::pin_mut!(images);
+        while let Some(image_batch_event) = images.next().await {
+            // Sends a batch of images into the main event loop
+            if !push_event(&event_sender, image_batch_event) {
+                return;
+            }
+
+            if image_queue.is_idle() {
+                let retry_stats = disney.retry_stats();
+                let in_flight = disney.images_in_flight();
+                info!(
+                    "Finished loading tile images duration = {} millis, requests = {}, retries = {}, failures = {}, image loads = {}, shared = {}",
+                    start_time.elapsed().as_millis(),
+                    retry_stats.requests(),
+                    retry_stats.retries(),
+                    retry_stats.failures(),
+                    in_flight.misses(),
+                    in_flight.hits()
+                );
+            }
+        }
+    });
+}
+
+/// Background loads the next page of a shelf and its tile images,
+/// piping them through to the event loop
+fn background_load_page(
+    tasks: &mut Tasks,
+    page_request: PageRequest,
+    disney: Arc<DisneyService>,
+    image_queue: Arc<ImageQueue>,
+    image_size: String,
+    event_sender: EventSender,
+) {
+    tasks.spawn(async move {
+        let PageRequest {
+            shelf_index,
+            set_id,
+            offset,
+            page_size,
+            style,
+        } = page_request;
+
+        // the page is styled to match the shelf so its tiles load the same image variants
+        let content_set = disney
+            .load_set_page(&set_id, offset, page_size)
+            .await
+            .map(|cs| cs.with_style(style))
+            .map_err(|e| warn!("Failed loading page {} of set {}: {:?}", offset, set_id, e))
+            .ok();
+
+        // the page's tiles are added after the tiles already on the shelf
+        let first = TileRef {
+            shelf: shelf_index,
+            tile: offset,
+        };
+        let requests = content_set
+            .as_ref()
+            .map(|cs| TileImageRequest::for_content_set(first, cs, &image_size))
+            .unwrap_or_default();
+
+        let page_load_event = ContentSetPageLoadEvent {
+            shelf_index,
+            content_set,
+        };
+        if push_event(&event_sender, page_load_event) {
+            image_queue.push(requests);
+        }
+    });
+}
+
+/// Background loads images for the details page or hero carousel,
+/// piping each through to the event loop as its own event
+fn background_load_images<E: Send + 'static>(
+    tasks: &mut Tasks,
+    img_urls: Vec<String>,
+    disney: Arc<DisneyService>,
+    event_sender: EventSender,
+    to_event: fn(String, Bytes) -> E,
+) {
+    if img_urls.is_empty() {
+        return;
+    }
+    tasks.spawn(async move {
+        let mut images = disney.stream_images(img_urls);
+        while let Some((img_url, bytes)) = images.next().await {
+            if !push_event(&event_sender, to_event(img_url, bytes)) {
+                return;
+            }
+        }
+    });
+}
+
+/// Background loads the hero carousel's images, piping them through
+/// to the event loop, failures too so the carousel stops waiting for them
+fn background_load_hero_images(
+    tasks: &mut Tasks,
+    img_urls: Vec<String>,
+    disney: Arc<DisneyService>,
+    event_sender: EventSender,
+) {
+    if img_urls.is_empty() {
+        return;
+    }
+    tasks.spawn(async move {
+        let mut images = disney.stream_image_results(img_urls);
+        while let Some((img_url, bytes)) = images.next().await {
+            if !push_event(&event_sender, HeroImageLoadEvent { img_url, bytes }) {
+                return;
+            }
+        }
+    });
+}
+
+/// Sends an event into the main event loop, false if the loop has
+/// gone away, e.g. while quitting, and the sender should stop
+fn push_event<E: 'static>(event_sender: &EventSender, event: E) -> bool {
+    match event_sender.push_custom_event(event) {
+        Ok(()) => true,
+        Err(e) => {
+            debug!("Unable to push custom event, stopping: {}", e);
+            false
+        }
+    }
+}
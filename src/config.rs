@@ -0,0 +1,464 @@
+//! Startup configuration for the application
+//!
+//! Every setting can come from (highest precedence first)
+//!
+//! 1. a command line flag, e.g. `--window-width 1280`
+//! 2. an environment variable, e.g. `DISNEY_WINDOW_WIDTH=1280`
+//! 3. the toml config file, `--config <path>` or `./disney.toml` if present
+//! 4. the defaults below
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use serde::Deserialize;
+
+use crate::input::Bindings;
+use crate::service::retry::RetryPolicy;
+use crate::service::source::{ContentSource, DEFAULT_BASE_URL};
+use crate::ui::animation::Easing;
+
+const DEFAULT_CONFIG_FILE: &str = "disney.toml";
+const DEFAULT_WINDOW_WIDTH: u32 = 1920;
+const DEFAULT_WINDOW_HEIGHT: u32 = 1080;
+const DEFAULT_CONCURRENCY: usize = 20;
+const DEFAULT_IMAGE_SIZE: &str = "1.78";
+const DEFAULT_FONT_PATH: &str = "./assets/Roboto-Regular.ttf";
+const DEFAULT_FONT_SIZE: u16 = 18;
+const DEFAULT_SHELF_PADDING: u32 = 50;
+const DEFAULT_SHELF_HEIGHT: u32 = 180;
+const DEFAULT_TILE_PADDING: u32 = 20;
+const DEFAULT_CONTENT_SOURCE: &str = "network";
+const DEFAULT_IMAGE_CACHE_DIR: &str = "./.cache/images";
+const DEFAULT_IMAGE_CACHE_MAX_BYTES: u64 = 256 * 1024 * 1024;
+const DEFAULT_TEXTURE_MAX_BYTES: u64 = 128 * 1024 * 1024;
+const DEFAULT_RETRY_MAX_ATTEMPTS: u32 = 3;
+const DEFAULT_RETRY_BASE_DELAY_MS: u64 = 200;
+const DEFAULT_RETRY_MAX_DELAY_MS: u64 = 5_000;
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 5_000;
+const DEFAULT_REQUEST_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_SCROLL_ANIMATION_MS: u64 = 250;
+const DEFAULT_FOCUS_ANIMATION_MS: u64 = 150;
+const DEFAULT_ANIMATION_EASING: &str = "ease_out";
+const DEFAULT_HERO_INTERVAL_MS: u64 = 8_000;
+const DEFAULT_FADE_ANIMATION_MS: u64 = 400;
+
+/// Command line flags, each of which can also be set
+/// via its environment variable
+#[derive(Debug, Default, Parser)]
+#[clap(name = "disney", about = "Renders a demo Disney home screen")]
+struct Args {
+    /// Path to a toml config file
+    #[clap(long, short = 'c', env = "DISNEY_CONFIG")]
+    config: Option<PathBuf>,
+
+    #[clap(long, env = "DISNEY_WINDOW_WIDTH")]
+    window_width: Option<u32>,
+
+    #[clap(long, env = "DISNEY_WINDOW_HEIGHT")]
+    window_height: Option<u32>,
+
+    /// Number of concurrent http requests
+    #[clap(long, env = "DISNEY_CONCURRENCY")]
+    concurrency: Option<usize>,
+
+    /// Tile image aspect ratio, e.g. 1.78
+    #[clap(long, env = "DISNEY_IMAGE_SIZE")]
+    image_size: Option<String>,
+
+    #[clap(long, env = "DISNEY_FONT_PATH")]
+    font_path: Option<PathBuf>,
+
+    #[clap(long, env = "DISNEY_FONT_SIZE")]
+    font_size: Option<u16>,
+
+    /// Vertical space between shelves
+    #[clap(long, env = "DISNEY_SHELF_PADDING")]
+    shelf_padding: Option<u32>,
+
+    #[clap(long, env = "DISNEY_SHELF_HEIGHT")]
+    shelf_height: Option<u32>,
+
+    /// Horizontal space between tiles
+    #[clap(long, env = "DISNEY_TILE_PADDING")]
+    tile_padding: Option<u32>,
+
+    /// Base url of the home screen and set ref json
+    #[clap(long, env = "DISNEY_BASE_URL")]
+    base_url: Option<String>,
+
+    /// network, dir:<path> or bundle:<path>
+    #[clap(long, env = "DISNEY_CONTENT_SOURCE")]
+    content_source: Option<String>,
+
+    /// Directory for the on-disk tile image cache
+    #[clap(long, env = "DISNEY_IMAGE_CACHE_DIR")]
+    image_cache_dir: Option<PathBuf>,
+
+    /// Byte budget for the image cache, 0 disables the cache
+    #[clap(long, env = "DISNEY_IMAGE_CACHE_MAX_BYTES")]
+    image_cache_max_bytes: Option<u64>,
+
+    /// Gpu memory budget for decoded textures
+    #[clap(long, env = "DISNEY_TEXTURE_MAX_BYTES")]
+    texture_max_bytes: Option<u64>,
+
+    /// Attempts per http request, including the first
+    #[clap(long, env = "DISNEY_RETRY_MAX_ATTEMPTS")]
+    retry_max_attempts: Option<u32>,
+
+    /// Delay before the first retry, doubled for each retry after
+    #[clap(long, env = "DISNEY_RETRY_BASE_DELAY_MS")]
+    retry_base_delay_ms: Option<u64>,
+
+    #[clap(long, env = "DISNEY_RETRY_MAX_DELAY_MS")]
+    retry_max_delay_ms: Option<u64>,
+
+    #[clap(long, env = "DISNEY_CONNECT_TIMEOUT_MS")]
+    connect_timeout_ms: Option<u64>,
+
+    /// Timeout for an entire http request, including the body
+    #[clap(long, env = "DISNEY_REQUEST_TIMEOUT_MS")]
+    request_timeout_ms: Option<u64>,
+
+    /// Time for the shelves to scroll to the selection, 0 disables the animation
+    #[clap(long, env = "DISNEY_SCROLL_ANIMATION_MS")]
+    scroll_animation_ms: Option<u64>,
+
+    /// Time for a tile to grow as it is selected, 0 disables the animation
+    #[clap(long, env = "DISNEY_FOCUS_ANIMATION_MS")]
+    focus_animation_ms: Option<u64>,
+
+    /// linear, ease_out or ease_in_out
+    #[clap(long, env = "DISNEY_ANIMATION_EASING")]
+    animation_easing: Option<String>,
+
+    /// Time before the hero carousel moves to the next program, 0 never moves
+    #[clap(long, env = "DISNEY_HERO_INTERVAL_MS")]
+    hero_interval_ms: Option<u64>,
+
+    /// Time for the background to crossfade to the focused program's art, 0 cuts
+    #[clap(long, env = "DISNEY_FADE_ANIMATION_MS")]
+    fade_animation_ms: Option<u64>,
+}
+
+/// Settings read from the toml config file,
+/// the keys are the same as the command line flags
+/// using snake case, e.g. `window_width = 1280`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FileConfig {
+    window_width: Option<u32>,
+    window_height: Option<u32>,
+    concurrency: Option<usize>,
+    image_size: Option<String>,
+    font_path: Option<PathBuf>,
+    font_size: Option<u16>,
+    shelf_padding: Option<u32>,
+    shelf_height: Option<u32>,
+    tile_padding: Option<u32>,
+    base_url: Option<String>,
+    content_source: Option<String>,
+    image_cache_dir: Option<PathBuf>,
+    image_cache_max_bytes: Option<u64>,
+    texture_max_bytes: Option<u64>,
+    retry_max_attempts: Option<u32>,
+    retry_base_delay_ms: Option<u64>,
+    retry_max_delay_ms: Option<u64>,
+    connect_timeout_ms: Option<u64>,
+    request_timeout_ms: Option<u64>,
+    scroll_animation_ms: Option<u64>,
+    focus_animation_ms: Option<u64>,
+    animation_easing: Option<String>,
+    hero_interval_ms: Option<u64>,
+    fade_animation_ms: Option<u64>,
+    /// e.g. `page_right = ["PageDown", "]"]`, see [`Bindings::new`]
+    #[serde(default)]
+    key_bindings: HashMap<String, Vec<String>>,
+    /// e.g. `select = ["a", "start"]`
+    #[serde(default)]
+    controller_bindings: HashMap<String, Vec<String>>,
+}
+impl FileConfig {
+    fn load(path: &Path) -> Result<FileConfig> {
+        let data =
+            std::fs::read_to_string(path).context(format!("Reading config file {:?}", path))?;
+        toml::from_str(&data).context(format!("Parsing config file {:?}", path))
+    }
+}
+
+/// The resolved, validated configuration
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub window_width: u32,
+    pub window_height: u32,
+    pub concurrency: usize,
+    pub image_size: String,
+    pub font_path: PathBuf,
+    pub font_size: u16,
+    pub shelf_padding: u32,
+    pub shelf_height: u32,
+    pub tile_padding: u32,
+    pub base_url: String,
+    pub content_source: String,
+    pub image_cache_dir: PathBuf,
+    pub image_cache_max_bytes: u64,
+    pub texture_max_bytes: u64,
+    pub retry_max_attempts: u32,
+    pub retry_base_delay_ms: u64,
+    pub retry_max_delay_ms: u64,
+    pub connect_timeout_ms: u64,
+    pub request_timeout_ms: u64,
+    pub scroll_animation_ms: u64,
+    pub focus_animation_ms: u64,
+    pub animation_easing: String,
+    pub hero_interval_ms: u64,
+    pub fade_animation_ms: u64,
+    /// Only from the config file, by action name
+    pub key_bindings: HashMap<String, Vec<String>>,
+    pub controller_bindings: HashMap<String, Vec<String>>,
+}
+/// The defaults alone, ignoring flags, environment and config file
+impl Default for Config {
+    fn default() -> Self {
+        Config::merge(Args::default(), FileConfig::default())
+    }
+}
+impl Config {
+    /// Loads the config from the process arguments,
+    /// environment and config file
+    ///
+    /// Exits the process on `--help` or invalid flags
+    pub fn load() -> Result<Config> {
+        Config::from_args(Args::parse())
+    }
+
+    /// Loads the config from the given arguments, the first being
+    /// the program name, and the environment and config file
+    ///
+    /// Invalid flags are returned as errors rather than exiting
+    pub fn load_from<I, T>(args: I) -> Result<Config>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        Config::from_args(Args::try_parse_from(args)?)
+    }
+
+    fn from_args(args: Args) -> Result<Config> {
+        // an explicitly requested config file must exist,
+        // the default one is optional
+        let file = match &args.config {
+            Some(path) => FileConfig::load(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                FileConfig::load(Path::new(DEFAULT_CONFIG_FILE))?
+            }
+            None => FileConfig::default(),
+        };
+
+        let config = Config::merge(args, file);
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Combines the flags (which already include the environment)
+    /// with the config file, falling back to defaults
+    fn merge(args: Args, file: FileConfig) -> Config {
+        Config {
+            window_width: args
+                .window_width
+                .or(file.window_width)
+                .unwrap_or(DEFAULT_WINDOW_WIDTH),
+            window_height: args
+                .window_height
+                .or(file.window_height)
+                .unwrap_or(DEFAULT_WINDOW_HEIGHT),
+            concurrency: args
+                .concurrency
+                .or(file.concurrency)
+                .unwrap_or(DEFAULT_CONCURRENCY),
+            image_size: args
+                .image_size
+                .or(file.image_size)
+                .unwrap_or_else(|| DEFAULT_IMAGE_SIZE.to_string()),
+            font_path: args
+                .font_path
+                .or(file.font_path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_FONT_PATH)),
+            font_size: args
+                .font_size
+                .or(file.font_size)
+                .unwrap_or(DEFAULT_FONT_SIZE),
+            shelf_padding: args
+                .shelf_padding
+                .or(file.shelf_padding)
+                .unwrap_or(DEFAULT_SHELF_PADDING),
+            shelf_height: args
+                .shelf_height
+                .or(file.shelf_height)
+                .unwrap_or(DEFAULT_SHELF_HEIGHT),
+            tile_padding: args
+                .tile_padding
+                .or(file.tile_padding)
+                .unwrap_or(DEFAULT_TILE_PADDING),
+            base_url: args
+                .base_url
+                .or(file.base_url)
+                .unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+            content_source: args
+                .content_source
+                .or(file.content_source)
+                .unwrap_or_else(|| DEFAULT_CONTENT_SOURCE.to_string()),
+            image_cache_dir: args
+                .image_cache_dir
+                .or(file.image_cache_dir)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_IMAGE_CACHE_DIR)),
+            image_cache_max_bytes: args
+                .image_cache_max_bytes
+                .or(file.image_cache_max_bytes)
+                .unwrap_or(DEFAULT_IMAGE_CACHE_MAX_BYTES),
+            texture_max_bytes: args
+                .texture_max_bytes
+                .or(file.texture_max_bytes)
+                .unwrap_or(DEFAULT_TEXTURE_MAX_BYTES),
+            retry_max_attempts: args
+                .retry_max_attempts
+                .or(file.retry_max_attempts)
+                .unwrap_or(DEFAULT_RETRY_MAX_ATTEMPTS),
+            retry_base_delay_ms: args
+                .retry_base_delay_ms
+                .or(file.retry_base_delay_ms)
+                .unwrap_or(DEFAULT_RETRY_BASE_DELAY_MS),
+            retry_max_delay_ms: args
+                .retry_max_delay_ms
+                .or(file.retry_max_delay_ms)
+                .unwrap_or(DEFAULT_RETRY_MAX_DELAY_MS),
+            connect_timeout_ms: args
+                .connect_timeout_ms
+                .or(file.connect_timeout_ms)
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS),
+            request_timeout_ms: args
+                .request_timeout_ms
+                .or(file.request_timeout_ms)
+                .unwrap_or(DEFAULT_REQUEST_TIMEOUT_MS),
+            scroll_animation_ms: args
+                .scroll_animation_ms
+                .or(file.scroll_animation_ms)
+                .unwrap_or(DEFAULT_SCROLL_ANIMATION_MS),
+            focus_animation_ms: args
+                .focus_animation_ms
+                .or(file.focus_animation_ms)
+                .unwrap_or(DEFAULT_FOCUS_ANIMATION_MS),
+            animation_easing: args
+                .animation_easing
+                .or(file.animation_easing)
+                .unwrap_or_else(|| DEFAULT_ANIMATION_EASING.to_string()),
+            hero_interval_ms: args
+                .hero_interval_ms
+                .or(file.hero_interval_ms)
+                .unwrap_or(DEFAULT_HERO_INTERVAL_MS),
+            fade_animation_ms: args
+                .fade_animation_ms
+                .or(file.fade_animation_ms)
+                .unwrap_or(DEFAULT_FADE_ANIMATION_MS),
+            key_bindings: file.key_bindings,
+            controller_bindings: file.controller_bindings,
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.window_width == 0 || self.window_height == 0 {
+            return Err(anyhow!(
+                "Invalid window size {}x{}, width and height must be greater than 0",
+                self.window_width,
+                self.window_height
+            ));
+        }
+        if self.concurrency == 0 {
+            return Err(anyhow!("Invalid concurrency 0, must be at least 1"));
+        }
+        match self.image_size.parse::<f32>() {
+            Ok(ratio) if ratio > 0.0 => {}
+            _ => {
+                return Err(anyhow!(
+                    "Invalid image size {:?}, expected an aspect ratio like 1.78",
+                    self.image_size
+                ))
+            }
+        }
+        if self.retry_max_attempts == 0 {
+            return Err(anyhow!("Invalid retry max attempts 0, must be at least 1"));
+        }
+        if self.retry_base_delay_ms > self.retry_max_delay_ms {
+            return Err(anyhow!(
+                "Invalid retry base delay {} millis, must not exceed the max delay {} millis",
+                self.retry_base_delay_ms,
+                self.retry_max_delay_ms
+            ));
+        }
+        if self.connect_timeout_ms == 0 || self.request_timeout_ms == 0 {
+            return Err(anyhow!(
+                "Invalid timeout 0, timeouts must be at least 1 milli"
+            ));
+        }
+        if Easing::from_name(&self.animation_easing).is_none() {
+            return Err(anyhow!(
+                "Invalid animation easing {:?}, expected one of {:?}",
+                self.animation_easing,
+                Easing::names()
+            ));
+        }
+        if self.font_size == 0 {
+            return Err(anyhow!("Invalid font size 0, must be at least 1"));
+        }
+        if !self.font_path.is_file() {
+            return Err(anyhow!("Font file {:?} does not exist", self.font_path));
+        }
+        if self.shelf_height <= self.tile_padding {
+            return Err(anyhow!(
+                "Invalid shelf height {}, must be larger than the tile padding {}",
+                self.shelf_height,
+                self.tile_padding
+            ));
+        }
+        if !self.base_url.starts_with("http://") && !self.base_url.starts_with("https://") {
+            return Err(anyhow!(
+                "Invalid base url {:?}, must be http or https",
+                self.base_url
+            ));
+        }
+        self.bindings()
+            .context("Invalid key_bindings or controller_bindings")?;
+        Ok(())
+    }
+
+    /// The default input bindings, with any from the config file
+    pub fn bindings(&self) -> Result<Bindings> {
+        Bindings::new(&self.key_bindings, &self.controller_bindings)
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_max_attempts,
+            base_delay: Duration::from_millis(self.retry_base_delay_ms),
+            max_delay: Duration::from_millis(self.retry_max_delay_ms),
+            connect_timeout: Duration::from_millis(self.connect_timeout_ms),
+            request_timeout: Duration::from_millis(self.request_timeout_ms),
+        }
+    }
+
+    /// The easing for ui animations, validated at startup
+    pub fn animation_easing(&self) -> Easing {
+        Easing::from_name(&self.animation_easing).unwrap_or(Easing::EaseOut)
+    }
+
+    /// The content source, the plain `network` source uses the configured base url
+    pub fn content_source(&self) -> Result<ContentSource> {
+        if self.content_source == "network" {
+            Ok(ContentSource::network(&self.base_url))
+        } else {
+            ContentSource::parse(&self.content_source)
+        }
+    }
+}
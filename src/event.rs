@@ -1,11 +1,55 @@
+//! Events sent from background loading into the
+//! event loop, via SDL custom user events
 use bytes::Bytes;
 
+use crate::model::home::ContentSet;
+
+/// Sent as each content set finishes loading,
+/// in home screen order
+pub struct ContentSetLoadEvent {
+    pub content_set: ContentSet,
+}
+
+/// A further page of a shelf has loaded, or failed to load
+pub struct ContentSetPageLoadEvent {
+    pub shelf_index: usize,
+    pub content_set: Option<ContentSet>,
+}
+
 pub struct ImageLoadBatchEvent {
     pub events: Vec<ImageLoadEvent>,
 }
 
+/// A tile on the home page by position, shelves and tiles are
+/// only ever added at the end, so a tile keeps its position
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileRef {
+    pub shelf: usize,
+    pub tile: usize,
+}
+
+/// A tile image has loaded, for every tile in the
+/// content set that shows it
 pub struct ImageLoadEvent {
     pub img_url: String,
     pub bytes: Bytes,
-    pub content_set_title: String,
+    pub tiles: Vec<TileRef>,
+}
+
+/// An image for the details page has loaded
+pub struct DetailsImageLoadEvent {
+    pub img_url: String,
+    pub bytes: Bytes,
+}
+
+/// Background art for the focused tile, or one near it, has loaded
+pub struct BackgroundImageLoadEvent {
+    pub img_url: String,
+    pub bytes: Bytes,
+}
+
+/// An image for the hero carousel has loaded, or failed to load
+pub struct HeroImageLoadEvent {
+    pub img_url: String,
+    pub bytes: Option<Bytes>,
 }
@@ -0,0 +1,337 @@
+//! Translates raw input, keys on a keyboard or remote and buttons on
+//! a game controller, into the semantic [`Action`]s the ui responds to
+//!
+//! The default bindings can be replaced per action in the config file,
+//! see [`Bindings::new`]
+//!
+//! The mouse, trackpads and touch screens point at things instead,
+//! and are translated into [`Pointer`] gestures by a [`PointerTracker`]
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// What the user asked for, regardless of the device they used
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    /// Move the selection one tile or row
+    Navigate(Direction),
+    /// Open the selected program
+    Select,
+    /// Close the details page, or go back to the top of the home page
+    Back,
+    /// Back, and quit once there is nowhere to go back to
+    Quit,
+    /// Jump a screen's worth of tiles along the shelf
+    PageLeft,
+    PageRight,
+}
+impl Action {
+    /// Every action, by the name used in the config file
+    const NAMES: &'static [(&'static str, Action)] = &[
+        ("up", Action::Navigate(Direction::Up)),
+        ("down", Action::Navigate(Direction::Down)),
+        ("left", Action::Navigate(Direction::Left)),
+        ("right", Action::Navigate(Direction::Right)),
+        ("select", Action::Select),
+        ("back", Action::Back),
+        ("quit", Action::Quit),
+        ("page_left", Action::PageLeft),
+        ("page_right", Action::PageRight),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+/// Maps keys and controller buttons to actions
+#[derive(Clone, Debug)]
+pub struct Bindings {
+    keys: HashMap<Keycode, Action>,
+    buttons: HashMap<Button, Action>,
+}
+impl Default for Bindings {
+    /// Arrow keys or the d-pad navigate, enter or A selects, backspace
+    /// or B goes back, escape quits, page up / down or the shoulders page
+    fn default() -> Self {
+        let keys = [
+            (Keycode::Up, Action::Navigate(Direction::Up)),
+            (Keycode::Down, Action::Navigate(Direction::Down)),
+            (Keycode::Left, Action::Navigate(Direction::Left)),
+            (Keycode::Right, Action::Navigate(Direction::Right)),
+            (Keycode::Return, Action::Select),
+            (Keycode::KpEnter, Action::Select),
+            (Keycode::Escape, Action::Quit),
+            (Keycode::Backspace, Action::Back),
+            // the back key on android tv remotes
+            (Keycode::AcBack, Action::Back),
+            (Keycode::PageUp, Action::PageLeft),
+            (Keycode::PageDown, Action::PageRight),
+        ];
+        let buttons = [
+            (Button::DPadUp, Action::Navigate(Direction::Up)),
+            (Button::DPadDown, Action::Navigate(Direction::Down)),
+            (Button::DPadLeft, Action::Navigate(Direction::Left)),
+            (Button::DPadRight, Action::Navigate(Direction::Right)),
+            (Button::A, Action::Select),
+            (Button::B, Action::Back),
+            (Button::LeftShoulder, Action::PageLeft),
+            (Button::RightShoulder, Action::PageRight),
+        ];
+        Bindings {
+            keys: keys.into_iter().collect(),
+            buttons: buttons.into_iter().collect(),
+        }
+    }
+}
+impl Bindings {
+    /// The default bindings, with the bindings for some actions replaced
+    ///
+    /// Both maps are keyed by action name, e.g. `page_right`, to the
+    /// SDL names of the keys (`PageDown`) or buttons (`rightshoulder`)
+    /// that trigger it. An action that is listed loses its default bindings
+    pub fn new(
+        key_bindings: &HashMap<String, Vec<String>>,
+        controller_bindings: &HashMap<String, Vec<String>>,
+    ) -> Result<Bindings> {
+        let mut bindings = Bindings::default();
+        for (action_name, key_names) in key_bindings {
+            let action = parse_action(action_name)?;
+            bindings.keys.retain(|_, bound| *bound != action);
+            for key_name in key_names {
+                let key = Keycode::from_name(key_name)
+                    .ok_or_else(|| anyhow!("Unknown key {:?} for {}", key_name, action_name))?;
+                bindings.keys.insert(key, action);
+            }
+        }
+        for (action_name, button_names) in controller_bindings {
+            let action = parse_action(action_name)?;
+            bindings.buttons.retain(|_, bound| *bound != action);
+            for button_name in button_names {
+                let button = Button::from_string(button_name).ok_or_else(|| {
+                    anyhow!("Unknown button {:?} for {}", button_name, action_name)
+                })?;
+                bindings.buttons.insert(button, action);
+            }
+        }
+        Ok(bindings)
+    }
+
+    /// The action for a key or button press, None for any other event
+    pub fn action(&self, event: &Event) -> Option<Action> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(key), ..
+            } => self.key_action(*key),
+            Event::ControllerButtonDown { button, .. } => self.buttons.get(button).copied(),
+            _ => None,
+        }
+    }
+
+    pub fn key_action(&self, key: Keycode) -> Option<Action> {
+        self.keys.get(&key).copied()
+    }
+}
+
+fn parse_action(name: &str) -> Result<Action> {
+    Action::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = Action::NAMES.iter().map(|(name, _)| *name).collect();
+        anyhow!("Unknown action {:?}, expected one of {:?}", name, names)
+    })
+}
+
+/// SDL also reports touches as mouse events from this mouse,
+/// touches are handled from the finger events instead
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+/// A touch that moves less than this many pixels is a tap
+const TAP_DISTANCE: f32 = 12.0;
+
+/// A gesture from the mouse, trackpad or a touch screen,
+/// positions are in drawable pixels, the same as the layout
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pointer {
+    /// The mouse moved over a point
+    Hover { x: i32, y: i32 },
+    /// A click, or a touch that did not move
+    Tap { x: i32, y: i32 },
+    /// The wheel or a trackpad scrolled while over a point, in
+    /// notches, positive is to the right and down
+    Wheel { x: i32, y: i32, dx: f32, dy: f32 },
+    /// A touch moved, in pixels
+    Drag { x: i32, y: i32, dx: f32, dy: f32 },
+    /// A moving touch was lifted, with its speed in pixels a second
+    Fling { x: i32, y: i32, velocity_x: f32 },
+}
+
+/// A finger on a touch screen
+struct Touch {
+    finger_id: i64,
+    start: (f32, f32),
+    last: (f32, f32),
+    last_timestamp: u32,
+    moved: bool,
+    velocity_x: f32,
+}
+
+/// Turns mouse, wheel and finger events into [`Pointer`] gestures
+///
+/// Mouse events are in window coordinates and finger events are
+/// normalized to the window, both are scaled to drawable pixels
+pub struct PointerTracker {
+    window_size: (u32, u32),
+    drawable_size: (u32, u32),
+    position: (i32, i32),
+    touch: Option<Touch>,
+}
+impl PointerTracker {
+    pub fn new(window_size: (u32, u32), drawable_size: (u32, u32)) -> PointerTracker {
+        PointerTracker {
+            window_size,
+            drawable_size,
+            position: (0, 0),
+            touch: None,
+        }
+    }
+
+    /// Call when the window is resized
+    pub fn resize(&mut self, window_size: (u32, u32), drawable_size: (u32, u32)) {
+        self.window_size = window_size;
+        self.drawable_size = drawable_size;
+    }
+
+    /// The gesture for a pointer event, None for any other event
+    pub fn on_event(&mut self, event: &Event) -> Option<Pointer> {
+        match *event {
+            Event::MouseMotion { which, x, y, .. } if which != TOUCH_MOUSE_ID => {
+                self.position = self.to_pixels(x, y);
+                let (x, y) = self.position;
+                Some(Pointer::Hover { x, y })
+            }
+            Event::MouseButtonDown {
+                which,
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if which != TOUCH_MOUSE_ID => {
+                self.position = self.to_pixels(x, y);
+                let (x, y) = self.position;
+                Some(Pointer::Tap { x, y })
+            }
+            Event::MouseWheel {
+                which,
+                x: wheel_x,
+                y: wheel_y,
+                direction,
+                ..
+            } if which != TOUCH_MOUSE_ID => {
+                // SDL scrolls up for a positive y, and natural scrolling flips both
+                let flip = if direction == MouseWheelDirection::Flipped {
+                    -1.0
+                } else {
+                    1.0
+                };
+                let (x, y) = self.position;
+                Some(Pointer::Wheel {
+                    x,
+                    y,
+                    dx: wheel_x as f32 * flip,
+                    dy: -wheel_y as f32 * flip,
+                })
+            }
+            Event::FingerDown {
+                finger_id,
+                x,
+                y,
+                timestamp,
+                ..
+            } => {
+                // only the first finger is tracked
+                if self.touch.is_none() {
+                    let point = self.touch_to_pixels(x, y);
+                    self.touch = Some(Touch {
+                        finger_id,
+                        start: point,
+                        last: point,
+                        last_timestamp: timestamp,
+                        moved: false,
+                        velocity_x: 0.0,
+                    });
+                }
+                None
+            }
+            Event::FingerMotion {
+                finger_id,
+                x,
+                y,
+                timestamp,
+                ..
+            } => {
+                let point = self.touch_to_pixels(x, y);
+                let touch = self.touch.as_mut().filter(|t| t.finger_id == finger_id)?;
+                let (dx, dy) = (point.0 - touch.last.0, point.1 - touch.last.1);
+                let (from_x, from_y) = (point.0 - touch.start.0, point.1 - touch.start.1);
+                touch.moved |= (from_x * from_x + from_y * from_y).sqrt() > TAP_DISTANCE;
+
+                // smooth the speed, finger events are noisy
+                let elapsed = timestamp.saturating_sub(touch.last_timestamp).max(1) as f32 / 1000.0;
+                touch.velocity_x = touch.velocity_x * 0.5 + (dx / elapsed) * 0.5;
+                touch.last = point;
+                touch.last_timestamp = timestamp;
+
+                touch.moved.then_some(Pointer::Drag {
+                    x: point.0 as i32,
+                    y: point.1 as i32,
+                    dx,
+                    dy,
+                })
+            }
+            Event::FingerUp { finger_id, .. } => {
+                if self.touch.as_ref()?.finger_id != finger_id {
+                    return None;
+                }
+                let touch = self.touch.take()?;
+                let (x, y) = (touch.last.0 as i32, touch.last.1 as i32);
+                if touch.moved {
+                    Some(Pointer::Fling {
+                        x,
+                        y,
+                        velocity_x: touch.velocity_x,
+                    })
+                } else {
+                    Some(Pointer::Tap { x, y })
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn to_pixels(&self, x: i32, y: i32) -> (i32, i32) {
+        let scale_x = self.drawable_size.0 as f32 / self.window_size.0.max(1) as f32;
+        let scale_y = self.drawable_size.1 as f32 / self.window_size.1.max(1) as f32;
+        ((x as f32 * scale_x) as i32, (y as f32 * scale_y) as i32)
+    }
+
+    fn touch_to_pixels(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            x * self.drawable_size.0 as f32,
+            y * self.drawable_size.1 as f32,
+        )
+    }
+}
@@ -0,0 +1,44 @@
+//! A demo Disney+ home screen, rendered with SDL2
+//!
+//! The binary is a thin launcher around [`app::run`], everything
+//! else is here so that other tools can be built on the same code
+//!
+//! - [`config`] - settings from flags, environment and a toml file
+//! - [`service`] - loads the home screen, content sets and images,
+//!   see [`DisneyService`]
+//! - [`model`] - the home screen json, see [`ContentSet`] and [`ContentSetItem`]
+//! - [`ui`] - the pages and components, drawn onto a window
+//!   or offscreen with the `headless` feature, see `ui::headless`
+//! - [`input`] - keys and controller buttons, mapped to actions
+//! - [`event`] - events sent from background loading into the event loop
+//! - [`tasks`] - background loading, cancelled with the screen that started it
+//!
+//! e.g. listing every shelf on the home screen
+//!
+//! ```no_run
+//! use disney::service::source::DEFAULT_BASE_URL;
+//! use disney::{ContentSource, DisneyService};
+//! use futures::TryStreamExt;
+//!
+//! # async fn titles() -> anyhow::Result<()> {
+//! let disney = DisneyService::new(4, ContentSource::network(DEFAULT_BASE_URL));
+//! let content_sets: Vec<_> = disney.stream_home_content_sets().await?.try_collect().await?;
+//! for content_set in content_sets {
+//!     println!("{} ({} items)", content_set.title(), content_set.hits());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+pub mod app;
+pub mod config;
+pub mod event;
+pub mod input;
+pub mod model;
+pub mod service;
+pub mod tasks;
+pub mod ui;
+
+pub use config::Config;
+pub use model::home::{ContentSet, ContentSetItem};
+pub use service::disney::DisneyService;
+pub use service::source::ContentSource;
@@ -1,75 +1,110 @@
 //! Model for different types in the home screen and related json
 //!
 //! This JSON is difficult to parse, and should have a custom
-//! deserializer for everything.  I would use a streaming
-//! deserializer so that we can begin loading the screen
-//! as soon as the json arrives and is parsed
+//! deserializer for everything.  The home screen is read
+//! incrementally via [`crate::model::stream::ContainerParser`]
+//! so that we can begin loading the screen as soon as each
+//! container arrives, each container is then parsed with Serde
 //!
 //! This relies purely on strongly typed Serde
 //! for serialization, which has a lot of warts
 use std::collections::HashMap;
+use std::fmt;
 
+use anyhow::{anyhow, Result};
 use serde::Deserialize;
+use serde_json::Value;
 
+use crate::model::style::TileShape;
+
+/// The set types we know how to render, other set types
+/// are still loaded but reported as a [`SetRefWarning`]
+const KNOWN_SET_TYPES: &[&str] = &[
+    "CuratedSet",
+    "TrendingSet",
+    "PersonalizedCuratedSet",
+    "BecauseYouSet",
+];
+
+/// A set ref document, the set is keyed by its type
+/// under `data`, e.g. `{"data": {"CuratedSet": {...}}}`
 #[derive(Deserialize)]
 pub struct SetRef {
-    data: SetRefContainer,
+    data: HashMap<String, Value>,
 }
 impl SetRef {
-    pub fn content_set(self) -> Option<ContentSet> {
-        self.data.content_set()
-    }
-}
+    /// Finds the content set in the document, preferring known set types
+    ///
+    /// Anything unexpected that did not prevent loading
+    /// the set is returned as a warning
+    pub fn content_set(self) -> Result<(ContentSet, Vec<SetRefWarning>)> {
+        let mut warnings = Vec::new();
+        let mut entries: Vec<(String, Value)> = self.data.into_iter().collect();
+        // by name after that, so the same document always gives the same set
+        entries.sort_by(|(a, _), (b, _)| {
+            let unknown = |set_type: &String| !KNOWN_SET_TYPES.contains(&set_type.as_str());
+            unknown(a).cmp(&unknown(b)).then_with(|| a.cmp(b))
+        });
 
-#[derive(Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct SetRefContainer {
-    // TODO: This is a hack, we need to intelligently parse the JSON
-    curated_set: Option<ContentSet>,
-    trending_set: Option<ContentSet>,
-    personalized_curated_set: Option<ContentSet>,
-}
-impl SetRefContainer {
-    pub fn content_set(self) -> Option<ContentSet> {
-        self.curated_set
-            .or(self.trending_set)
-            .or(self.personalized_curated_set)
-    }
-}
+        let mut found: Option<ContentSet> = None;
+        for (set_type, value) in entries {
+            if found.is_some() {
+                warnings.push(SetRefWarning::ExtraSet(set_type));
+                continue;
+            }
+            match serde_json::from_value::<ContentSet>(value) {
+                Ok(mut cs) => {
+                    if !KNOWN_SET_TYPES.contains(&set_type.as_str()) {
+                        warnings.push(SetRefWarning::UnknownSetType(set_type.clone()));
+                    }
+                    cs.set_type.get_or_insert(set_type);
+                    found = Some(cs);
+                }
+                Err(e) => warnings.push(SetRefWarning::InvalidSet {
+                    set_type,
+                    error: e.to_string(),
+                }),
+            }
+        }
 
-#[derive(Deserialize)]
-pub struct HomeRoot {
-    data: HomeData,
-}
-impl HomeRoot {
-    pub fn home_screen(self) -> HomeScreen {
-        self.data.standard_collection
+        match found {
+            Some(cs) => Ok((cs, warnings)),
+            None => Err(anyhow!("Unable to find a content set, {:?}", warnings)),
+        }
     }
 }
 
-#[derive(Deserialize)]
-#[serde(rename_all = "PascalCase")]
-pub struct HomeData {
-    standard_collection: HomeScreen,
-}
-
-#[derive(Deserialize)]
-pub struct HomeScreen {
-    containers: Vec<Container>,
+/// Problems found in a set ref that did not stop it from loading
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SetRefWarning {
+    /// The set was loaded, but is of a type we do not know
+    UnknownSetType(String),
+    /// A set that could not be deserialized, and was skipped
+    InvalidSet { set_type: String, error: String },
+    /// More than one set was found, this one was ignored
+    ExtraSet(String),
 }
-impl HomeScreen {
-    pub fn content_sets(self) -> impl Iterator<Item = ContentSet> {
-        self.containers.into_iter().map(|c| c.content_set())
+impl fmt::Display for SetRefWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SetRefWarning::UnknownSetType(set_type) => write!(f, "unknown set type {}", set_type),
+            SetRefWarning::InvalidSet { set_type, error } => {
+                write!(f, "invalid set {}: {}", set_type, error)
+            }
+            SetRefWarning::ExtraSet(set_type) => write!(f, "ignored extra set {}", set_type),
+        }
     }
 }
 
 #[derive(Clone, Deserialize)]
 pub struct Container {
+    style: Option<String>, // e.g. editorial, TrendingSet
     set: ContentSet,
 }
 impl Container {
+    /// The content set, carrying the style of its container
     pub fn content_set(self) -> ContentSet {
-        self.set
+        self.set.with_style(self.style)
     }
 }
 
@@ -78,6 +113,14 @@ impl Container {
 pub struct ContentSet {
     text: ContentSetTitle,
     ref_id: Option<String>, // will be set if we need to load this separately
+    set_id: Option<String>,
+    #[serde(rename = "type")]
+    set_type: Option<String>, // e.g. CuratedSet, or SetRef for refs
+    content_class: Option<String>, // e.g. editorial
+    meta: Option<SetMeta>,
+    // from the container, set refs and pages do not have one
+    #[serde(skip)]
+    style: Option<String>,
 
     #[serde(default)]
     items: Vec<ContentSetItem>, // represents all of the programs in a curated set
@@ -92,6 +135,21 @@ impl ContentSet {
         &self.text.title.full.set.default.content
     }
 
+    /// Sets the style, a set ref or page takes the style
+    /// of the container it was loaded for
+    pub fn with_style(self, style: Option<String>) -> ContentSet {
+        ContentSet { style, ..self }
+    }
+
+    pub fn style(&self) -> Option<&String> {
+        self.style.as_ref()
+    }
+
+    /// The shape of the tiles for this set, see [`TileShape::for_style`]
+    pub fn tile_shape(&self) -> TileShape {
+        TileShape::for_style(self.style.as_deref())
+    }
+
     pub fn items(self) -> Vec<ContentSetItem> {
         self.items
     }
@@ -103,26 +161,273 @@ impl ContentSet {
     pub fn ref_id(&self) -> Option<&String> {
         self.ref_id.as_ref()
     }
+
+    pub fn set_id(&self) -> Option<&String> {
+        self.set_id.as_ref()
+    }
+
+    pub fn set_type(&self) -> Option<&String> {
+        self.set_type.as_ref()
+    }
+
+    pub fn content_class(&self) -> Option<&String> {
+        self.content_class.as_ref()
+    }
+
+    /// Total number of items in the set, across all pages
+    pub fn hits(&self) -> usize {
+        self.meta
+            .as_ref()
+            .map(|m| m.hits.max(m.offset + self.items.len()))
+            .unwrap_or(self.items.len())
+    }
+
+    /// Drops the paging information, so the items
+    /// are the whole set, for sources without pages
+    pub fn without_paging(self) -> ContentSet {
+        ContentSet { meta: None, ..self }
+    }
+
+    /// The number of items requested per page
+    pub fn page_size(&self) -> usize {
+        self.meta
+            .as_ref()
+            .and_then(|m| m.page_size)
+            .unwrap_or(self.items.len())
+    }
+}
+
+/// Paging information for a content set, the items
+/// are a single page starting at the offset
+#[derive(Clone, Debug, Deserialize)]
+pub struct SetMeta {
+    #[serde(default)]
+    hits: usize,
+    #[serde(default)]
+    offset: usize,
+    // home.json names this Home_size
+    #[serde(alias = "Home_size")]
+    page_size: Option<usize>,
+}
+
+/// The kind of program an item refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    Series,
+    Movie,
+    Collection,
+    Other,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ContentSetItem {
+    // an item missing either is still shown, as an untitled other
+    #[serde(rename = "type", default)]
+    item_type: Option<String>,
+    #[serde(default)]
+    text: ItemText,
     image: ContentItemTileImage,
+    // these can be null rather than empty
+    ratings: Option<Vec<Rating>>,
+    releases: Option<Vec<Release>>,
+    tags: Option<Vec<Tag>>,
+    media_rights: Option<MediaRights>,
+    current_availability: Option<Availability>,
 }
 impl ContentSetItem {
-    pub fn tile_image_url(&self, size: &str) -> Option<&String> {
-        self.image
-            .tile
-            .get(size)
-            .and_then(|ti| ti.default_image())
-            .map(|di| &di.default.url)
+    /// The artwork for a tile, or None if the item has no artwork at all
+    ///
+    /// Each kind of image is tried in turn, the tile, then the hero tile, then
+    /// the background. Within a kind, the sizes are tried in order, then
+    /// whichever variant is closest in shape to the first size
+    pub fn tile_image_url(&self, sizes: &[&str]) -> Option<&String> {
+        first_image_url(
+            &[
+                &self.image.tile,
+                &self.image.hero_tile,
+                &self.image.background,
+            ],
+            sizes,
+        )
+    }
+
+    /// The artwork for the hero carousel, the hero tile, then the
+    /// hero collection art, then the background, with the same
+    /// fallbacks as [`ContentSetItem::tile_image_url`]
+    pub fn hero_image_url(&self, sizes: &[&str]) -> Option<&String> {
+        first_image_url(
+            &[
+                &self.image.hero_tile,
+                &self.image.hero_collection,
+                &self.image.background,
+            ],
+            sizes,
+        )
+    }
+
+    /// The title treatment as a transparent layer the shape of
+    /// the hero art, to draw over it
+    pub fn title_treatment_layer_url(&self, sizes: &[&str]) -> Option<&String> {
+        first_image_url(&[&self.image.title_treatment_layer], sizes)
+    }
+
+    /// The full bleed background art, the variant closest
+    /// in shape if there is not one of the size
+    pub fn background_image_url(&self, size: &str) -> Option<&String> {
+        first_image_url(&[&self.image.background], &[size])
+    }
+
+    pub fn title_treatment_url(&self, size: &str) -> Option<&String> {
+        image_url(&self.image.title_treatment, size)
+    }
+
+    /// The display title, programs are titled by series,
+    /// program or collection depending on their type, any
+    /// other kind of title is picked by name so it is always the same one
+    pub fn title(&self) -> Option<&String> {
+        let full = &self.text.title.full;
+        full.get("series")
+            .or_else(|| full.get("program"))
+            .or_else(|| full.get("collection"))
+            .or_else(|| {
+                full.iter()
+                    .min_by_key(|(kind, _)| *kind)
+                    .map(|(_, text)| text)
+            })
+            .map(|text| &text.default.content)
+    }
+
+    pub fn kind(&self) -> ItemKind {
+        match self.item_type.as_deref() {
+            Some("DmcSeries") => ItemKind::Series,
+            Some("DmcVideo") => ItemKind::Movie,
+            Some("StandardCollection") => ItemKind::Collection,
+            _ => ItemKind::Other,
+        }
+    }
+
+    /// The first content rating, e.g. TV-14
+    pub fn rating(&self) -> Option<&String> {
+        self.ratings.iter().flatten().next().map(|r| &r.value)
+    }
+
+    pub fn release_year(&self) -> Option<u32> {
+        self.releases.iter().flatten().find_map(|r| r.release_year)
+    }
+
+    /// Is this a Disney+ Original
+    pub fn is_original(&self) -> bool {
+        self.tags
+            .iter()
+            .flatten()
+            .any(|t| t.tag_type == "disneyPlusOriginal" && t.value.as_deref() == Some("true"))
+    }
+
+    pub fn media_rights(&self) -> Option<&MediaRights> {
+        self.media_rights.as_ref()
+    }
+
+    pub fn current_availability(&self) -> Option<&Availability> {
+        self.current_availability.as_ref()
     }
 }
 
+/// The first kind of image that has a variant, trying the sizes in
+/// order and then whichever variant is closest to the first size
+fn first_image_url<'a>(
+    kinds: &[&'a HashMap<String, TileImage>],
+    sizes: &[&str],
+) -> Option<&'a String> {
+    kinds.iter().find_map(|images| {
+        sizes
+            .iter()
+            .find_map(|size| image_url(images, size))
+            .or_else(|| closest_image_url(images, sizes.first()?))
+    })
+}
+
+fn image_url<'a>(images: &'a HashMap<String, TileImage>, size: &str) -> Option<&'a String> {
+    images
+        .get(size)
+        .and_then(|ti| ti.default_image())
+        .map(|di| &di.default.url)
+}
+
+/// The variant closest in shape to the size, sizes are aspect ratios e.g. 1.78
+fn closest_image_url<'a>(images: &'a HashMap<String, TileImage>, size: &str) -> Option<&'a String> {
+    let target: f32 = size.parse().ok()?;
+    let mut sizes: Vec<(f32, &String)> = images
+        .keys()
+        .filter_map(|key| Some(((key.parse::<f32>().ok()? - target).abs(), key)))
+        .collect();
+    // ties go to the smaller key, so every caller picks the same image
+    sizes.sort_by(|(a, a_key), (b, b_key)| a.total_cmp(b).then_with(|| a_key.cmp(b_key)));
+    sizes
+        .into_iter()
+        .find_map(|(_, key)| image_url(images, key))
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ContentItemTileImage {
+    // any of these can be missing from bad catalog data
+    #[serde(default)]
     tile: HashMap<String, TileImage>,
+    #[serde(default)]
+    hero_tile: HashMap<String, TileImage>,
+    #[serde(default)]
+    background: HashMap<String, TileImage>,
+    #[serde(default)]
+    title_treatment: HashMap<String, TileImage>,
+    #[serde(default)]
+    title_treatment_layer: HashMap<String, TileImage>,
+    #[serde(default)]
+    hero_collection: HashMap<String, TileImage>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ItemText {
+    #[serde(default)]
+    title: ItemTitle,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct ItemTitle {
+    // keyed by series, program or collection
+    #[serde(default)]
+    full: HashMap<String, DefaultText>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Rating {
+    value: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    release_year: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Tag {
+    #[serde(rename = "type")]
+    tag_type: String,
+    value: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaRights {
+    #[serde(default)]
+    pub download_blocked: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Availability {
+    pub region: Option<String>,
+    pub kids_mode: Option<bool>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
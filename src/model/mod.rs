@@ -1 +1,4 @@
+//! The home screen json, deserialized with Serde
 pub mod home;
+pub mod stream;
+pub mod style;
@@ -0,0 +1,152 @@
+//! Incremental parsing of the home screen json
+//!
+//! The home screen is a large document, but everything we render
+//! lives in the `containers` array.  Rather than buffering the entire
+//! document, [`ContainerParser`] is fed bytes as they arrive and scans
+//! for the `containers` array, handing each [`Container`] to serde as
+//! soon as its closing brace is seen.
+//!
+//! Only the bytes of the container currently being read are buffered,
+//! everything else in the document is skipped over.
+use std::collections::VecDeque;
+
+use anyhow::{anyhow, Context, Error, Result};
+
+use crate::model::home::Container;
+
+const CONTAINERS_KEY: &[u8] = b"containers";
+
+pub struct ContainerParser {
+    /// nesting depth of objects and arrays
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// the last string read outside of a container, a candidate key
+    string: Vec<u8>,
+    /// set when we have read a key and are waiting for its value
+    key: Option<Vec<u8>>,
+    /// the depth of the containers array, once we have found it
+    containers_depth: Option<usize>,
+    /// the bytes of the container currently being read
+    container: Option<Vec<u8>>,
+    finished: bool,
+    parsed: VecDeque<Result<Container>>,
+}
+impl Default for ContainerParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ContainerParser {
+    pub fn new() -> ContainerParser {
+        ContainerParser {
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            string: Vec::new(),
+            key: None,
+            containers_depth: None,
+            container: None,
+            finished: false,
+            parsed: VecDeque::new(),
+        }
+    }
+
+    /// Feeds the next chunk of the document into the parser,
+    /// any containers that are completed by this chunk
+    /// are available from [`ContainerParser::next_container`]
+    pub fn feed(&mut self, chunk: &[u8]) {
+        for &b in chunk {
+            if self.finished {
+                return;
+            }
+            self.feed_byte(b);
+        }
+    }
+
+    /// Returns the next parsed container, if any
+    pub fn next_container(&mut self) -> Option<Result<Container>> {
+        self.parsed.pop_front()
+    }
+
+    /// Records a failure reading the document, ends parsing
+    pub fn fail(&mut self, error: Error) {
+        self.finished = true;
+        self.parsed.push_back(Err(error));
+    }
+
+    /// Signals the end of the document, records an error
+    /// if the containers array was missing or incomplete
+    pub fn finish(&mut self) {
+        if !self.finished {
+            let error = if self.containers_depth.is_some() {
+                anyhow!("Home screen ended before the containers were complete")
+            } else {
+                anyhow!("Home screen is missing the containers")
+            };
+            self.fail(error);
+        }
+    }
+
+    fn feed_byte(&mut self, b: u8) {
+        if let Some(container) = self.container.as_mut() {
+            container.push(b);
+        }
+
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if b == b'\\' {
+                self.escaped = true;
+            } else if b == b'"' {
+                self.in_string = false;
+            } else if self.container.is_none() {
+                self.string.push(b);
+            }
+            return;
+        }
+
+        match b {
+            b'"' => {
+                self.in_string = true;
+                self.string.clear();
+            }
+            b':' if self.container.is_none() => {
+                self.key = Some(std::mem::take(&mut self.string));
+            }
+            b'{' | b'[' => {
+                self.depth += 1;
+                let key = self.key.take();
+                match self.containers_depth {
+                    None if b == b'[' && key.as_deref() == Some(CONTAINERS_KEY) => {
+                        self.containers_depth = Some(self.depth);
+                    }
+                    Some(cd) if b == b'{' && self.depth == cd + 1 && self.container.is_none() => {
+                        self.container = Some(vec![b'{']);
+                    }
+                    _ => {}
+                }
+            }
+            b'}' | b']' => {
+                self.key = None;
+                if let Some(cd) = self.containers_depth {
+                    if self.depth == cd + 1 && b == b'}' {
+                        if let Some(bytes) = self.container.take() {
+                            self.parsed.push_back(
+                                serde_json::from_slice(&bytes).context("Deserializing container"),
+                            );
+                        }
+                    } else if self.depth == cd {
+                        // the containers array is closed, nothing else to read
+                        self.finished = true;
+                    }
+                }
+                self.depth = self.depth.saturating_sub(1);
+            }
+            b',' => {
+                self.key = None;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! How a shelf is presented, chosen by the `style` of its container
+//!
+//! Items carry several tile image variants, keyed by aspect ratio,
+//! e.g. `1.78` (16:9), `0.71` (posters) or `2.29` (wide)
+
+/// The shape of the tiles on a shelf
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileShape {
+    /// 16:9 tiles, most rows
+    Standard,
+    /// tall tiles, for rows of posters
+    Poster,
+    /// wide tiles, for rows that feature a few programs
+    Hero,
+}
+impl TileShape {
+    /// The shape for a container style, unknown styles are standard rows
+    pub fn for_style(style: Option<&str>) -> TileShape {
+        match style {
+            Some("TrendingSet") => TileShape::Poster,
+            Some("BecauseYouSet") => TileShape::Hero,
+            _ => TileShape::Standard,
+        }
+    }
+
+    /// Width over height of a tile, standard tiles are the shape
+    /// of the configured standard size, e.g. `1.33` for 4:3 tiles
+    pub fn aspect_ratio(self, standard_size: &str) -> f32 {
+        match self {
+            TileShape::Standard => standard_size.parse().unwrap_or(1.78),
+            TileShape::Poster => 0.71,
+            TileShape::Hero => 2.29,
+        }
+    }
+
+    /// The image variants to use, most preferred first
+    ///
+    /// Variants closest in shape come first, falling back to the
+    /// configured standard size, which most items have. A variant
+    /// of a different shape is cropped to fit the tile
+    pub fn image_sizes(self, standard_size: &str) -> Vec<&str> {
+        match self {
+            TileShape::Standard => vec![standard_size, "1.78"],
+            TileShape::Poster => vec!["0.71", "0.67", "0.75", standard_size],
+            TileShape::Hero => vec!["2.29", standard_size],
+        }
+    }
+}
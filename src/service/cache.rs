@@ -0,0 +1,292 @@
+//! Persistent on-disk cache of tile images
+//!
+//! Image urls are content addressed, so an image that has been
+//! downloaded once never needs to be downloaded again.  Each image
+//! is stored in its own file named by a hash of the url
+//!
+//! ```text
+//! magic "DIMG" | url len (u32) | url | body len (u64) | body checksum (u64) | body
+//! ```
+//!
+//! The url and checksum are verified on every read, a file that does not
+//! match (e.g. truncated by a power cut) is treated as a miss and removed.
+//! A file for a different url, whose name collided, is a miss but kept.
+//! Writes go to a temp file that is renamed into place, so readers never
+//! see a partial file.
+//!
+//! The cache is bounded by a byte budget, the least recently used images
+//! are evicted first.  File modification times record the last use so that
+//! the order survives restarts.
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
+use log::{info, warn};
+
+const MAGIC: &[u8; 4] = b"DIMG";
+const EXTENSION: &str = "img";
+const TMP_EXTENSION: &str = "tmp";
+
+struct Entry {
+    size: u64,
+    last_used: u64,
+}
+
+struct CacheIndex {
+    entries: HashMap<String, Entry>,
+    total_bytes: u64,
+    clock: u64,
+}
+impl CacheIndex {
+    fn touch(&mut self, name: &str) {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.last_used = self.clock;
+        }
+    }
+
+    fn insert(&mut self, name: String, size: u64) {
+        self.clock += 1;
+        let entry = Entry {
+            size,
+            last_used: self.clock,
+        };
+        if let Some(old) = self.entries.insert(name, entry) {
+            self.total_bytes -= old.size;
+        }
+        self.total_bytes += size;
+    }
+
+    fn remove(&mut self, name: &str) {
+        if let Some(old) = self.entries.remove(name) {
+            self.total_bytes -= old.size;
+        }
+    }
+
+    /// Names of entries to evict, least recently used first,
+    /// to bring the cache within the budget
+    fn evictions(&self, max_bytes: u64) -> Vec<String> {
+        if self.total_bytes <= max_bytes {
+            return Vec::new();
+        }
+        let mut by_age: Vec<(&String, &Entry)> = self.entries.iter().collect();
+        by_age.sort_by_key(|(_, entry)| entry.last_used);
+
+        let mut total = self.total_bytes;
+        by_age
+            .into_iter()
+            .take_while(|(_, entry)| {
+                let evict = total > max_bytes;
+                total -= entry.size;
+                evict
+            })
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+pub struct ImageCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<CacheIndex>,
+    tmp_counter: AtomicU64,
+}
+impl ImageCache {
+    /// Opens (creating if needed) the cache directory,
+    /// indexing any images already in it
+    pub fn open(dir: &Path, max_bytes: u64) -> Result<ImageCache> {
+        fs::create_dir_all(dir).context(format!("Creating image cache {:?}", dir))?;
+
+        let mut files = Vec::new();
+        for dir_entry in fs::read_dir(dir)? {
+            let path = dir_entry?.path();
+            match path.extension().and_then(|ext| ext.to_str()) {
+                Some(EXTENSION) => {
+                    let metadata = fs::metadata(&path)?;
+                    let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                        files.push((name.to_string(), metadata.len(), modified));
+                    }
+                }
+                // left behind by an interrupted write
+                Some(TMP_EXTENSION) => {
+                    let _ = fs::remove_file(&path);
+                }
+                _ => {}
+            }
+        }
+
+        // replay the files oldest first to rebuild the lru order
+        files.sort_by_key(|(_, _, modified)| *modified);
+        let mut index = CacheIndex {
+            entries: HashMap::new(),
+            total_bytes: 0,
+            clock: 0,
+        };
+        for (name, size, _) in files {
+            index.insert(name, size);
+        }
+        info!(
+            "Opened image cache {:?} with {} images, {} bytes",
+            dir,
+            index.entries.len(),
+            index.total_bytes
+        );
+
+        let cache = ImageCache {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            index: Mutex::new(index),
+            tmp_counter: AtomicU64::new(0),
+        };
+        // the budget may have shrunk since the last run
+        cache.evict();
+        Ok(cache)
+    }
+
+    /// Returns the cached image for the url, if present and intact
+    pub fn get(&self, url: &str) -> Option<Bytes> {
+        let name = file_name(url);
+        if !self.lock().entries.contains_key(&name) {
+            return None;
+        }
+
+        let path = self.dir.join(&name);
+        match fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|data| decode(url, data))
+        {
+            Ok(None) => None,
+            Ok(Some(body)) => {
+                self.lock().touch(&name);
+                // persist the access so lru order survives restarts
+                if let Ok(file) = File::options().write(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                Some(body)
+            }
+            Err(e) => {
+                warn!("Discarding corrupt cached image {:?}: {:?}", path, e);
+                let _ = fs::remove_file(&path);
+                self.lock().remove(&name);
+                None
+            }
+        }
+    }
+
+    /// Stores the image for the url, evicting older images
+    /// if the cache is over budget
+    pub fn put(&self, url: &str, body: &[u8]) -> Result<()> {
+        let data = encode(url, body);
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return Ok(());
+        }
+
+        let name = file_name(url);
+        let path = self.dir.join(&name);
+        let tmp_path = self.dir.join(format!(
+            "{}.{}.{}",
+            name,
+            self.tmp_counter.fetch_add(1, Ordering::Relaxed),
+            TMP_EXTENSION
+        ));
+
+        let write = || -> Result<()> {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, &path)?;
+            Ok(())
+        };
+        if let Err(e) = write() {
+            let _ = fs::remove_file(&tmp_path);
+            return Err(e.context(format!("Writing cached image {:?}", path)));
+        }
+
+        self.lock().insert(name, size);
+        self.evict();
+        Ok(())
+    }
+
+    fn evict(&self) {
+        let mut index = self.lock();
+        for name in index.evictions(self.max_bytes) {
+            if let Err(e) = fs::remove_file(self.dir.join(&name)) {
+                warn!("Unable to evict cached image {:?}: {:?}", name, e);
+            }
+            index.remove(&name);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, CacheIndex> {
+        // the index is always left consistent, so a panic
+        // elsewhere while holding the lock is not a problem
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Stable 64 bit FNV-1a hash, used for file names and checksums
+fn fnv1a<'a>(data: impl IntoIterator<Item = &'a u8>) -> u64 {
+    data.into_iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ *b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// 128 bits, the hash of the url forwards and backwards, so two
+/// urls sharing a file and replacing each other is vanishingly rare
+fn file_name(url: &str) -> String {
+    let url = url.as_bytes();
+    format!(
+        "{:016x}{:016x}.{}",
+        fnv1a(url),
+        fnv1a(url.iter().rev()),
+        EXTENSION
+    )
+}
+
+fn encode(url: &str, body: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(body.len() + url.len() + 24);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&(url.len() as u32).to_le_bytes());
+    data.extend_from_slice(url.as_bytes());
+    data.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    data.extend_from_slice(&fnv1a(body).to_le_bytes());
+    data.extend_from_slice(body);
+    data
+}
+
+/// The body of the image, None if the file is for a different url
+fn decode(url: &str, data: Vec<u8>) -> Result<Option<Bytes>> {
+    let mut data = Bytes::from(data);
+    let mut take = |n: usize| -> Result<Bytes> {
+        if data.len() < n {
+            return Err(anyhow!("Cached image is truncated"));
+        }
+        Ok(data.split_to(n))
+    };
+
+    if take(4)?.as_ref() != MAGIC {
+        return Err(anyhow!("Cached image has an invalid header"));
+    }
+    let url_len = u32::from_le_bytes(take(4)?.as_ref().try_into()?) as usize;
+    if take(url_len)?.as_ref() != url.as_bytes() {
+        return Ok(None);
+    }
+    let body_len = u64::from_le_bytes(take(8)?.as_ref().try_into()?) as usize;
+    let checksum = u64::from_le_bytes(take(8)?.as_ref().try_into()?);
+    let body = take(body_len)?;
+    if !data.is_empty() {
+        return Err(anyhow!("Cached image has trailing data"));
+    }
+    if fnv1a(&body) != checksum {
+        return Err(anyhow!("Cached image failed the checksum"));
+    }
+    Ok(Some(body))
+}
@@ -1,177 +1,388 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, Context, Error, Result};
 use bytes::Bytes;
-use futures::{stream, Stream, StreamExt};
-use log::{error, warn};
+use futures::{future, stream, Stream, StreamExt};
+use log::{debug, warn};
 use reqwest::{Client, StatusCode};
+use std::future::Future;
+use std::sync::Arc;
+use tokio::{task, time};
 
 use crate::{
     event::{ImageLoadBatchEvent, ImageLoadEvent},
-    model::home::{ContentSet, HomeRoot, HomeScreen, SetRef},
+    model::home::{Container, ContentSet, SetRef, SetRefWarning},
+    model::stream::ContainerParser,
+    service::cache::ImageCache,
+    service::image_queue::{ImageQueue, TileImageRequest},
+    service::in_flight::InFlight,
+    service::retry::{RetryPolicy, RetryStats, StatusError},
+    service::source::ContentSource,
 };
 
 pub struct DisneyService {
     concurrency: usize,
     http: Client,
+    source: ContentSource,
+    image_cache: Option<Arc<ImageCache>>,
+    retry_policy: RetryPolicy,
+    retry_stats: RetryStats,
+    images_in_flight: InFlight,
 }
 impl DisneyService {
-    pub fn new(concurrency: usize) -> Self {
+    pub fn new(concurrency: usize, source: ContentSource) -> Self {
         Self {
             concurrency,
             http: Client::new(),
+            source,
+            image_cache: None,
+            retry_policy: RetryPolicy::default(),
+            retry_stats: RetryStats::default(),
+            images_in_flight: InFlight::default(),
+        }
+    }
+
+    /// Sets the retry policy and timeouts used for all http requests
+    pub fn with_retry_policy(self, retry_policy: RetryPolicy) -> Result<Self> {
+        let http = Client::builder()
+            .connect_timeout(retry_policy.connect_timeout)
+            .timeout(retry_policy.request_timeout)
+            .build()?;
+        Ok(Self {
+            http,
+            retry_policy,
+            ..self
+        })
+    }
+
+    /// Request and retry counts since startup
+    pub fn retry_stats(&self) -> &RetryStats {
+        &self.retry_stats
+    }
+
+    /// Whether shelves can load further pages, see [`ContentSource::serves_pages`]
+    pub fn serves_pages(&self) -> bool {
+        self.source.serves_pages()
+    }
+
+    /// Image requests that shared a download already in flight,
+    /// and those that started one, since startup
+    pub fn images_in_flight(&self) -> &InFlight {
+        &self.images_in_flight
+    }
+
+    /// Consults the on-disk cache before downloading tile images
+    pub fn with_image_cache(self, image_cache: ImageCache) -> Self {
+        Self {
+            image_cache: Some(Arc::new(image_cache)),
+            ..self
         }
     }
 
     /// Produces an async stream that background
-    /// loads all of the iamges in a controlled manner
+    /// loads tile images from the queue in a controlled manner
     /// Can tweak parallelism in here
-    pub async fn stream_tile_images(
-        &self,
-        content_sets: Vec<ContentSet>,
-        image_size: String,
-    ) -> impl Stream<Item = ImageLoadBatchEvent> + '_ {
-        // Iterate over pairs (content_set_tile, tile_img_url)
-        // filtering out (and logging) those tile images without a url
-        let curated_items = content_sets.into_iter().flat_map(move |cs| {
-            let cs_title = cs.title().clone();
-            let img_size = image_size.clone();
-            cs.items().into_iter().filter_map(move |item| {
-                if let Some(img_url) = item.tile_image_url(&img_size) {
-                    Some((cs_title.clone(), img_url.clone()))
-                } else {
-                    warn!("No image found for size {:?} and item {:?}", img_size, item);
-                    None
-                }
-            })
+    ///
+    /// The next image is only taken from the queue when there is room
+    /// to load it, so it is the most important one at that moment, see
+    /// [`ImageQueue`]. The stream never ends, it waits for more images
+    pub fn stream_queued_images<'a>(
+        &'a self,
+        queue: &'a ImageQueue,
+    ) -> impl Stream<Item = ImageLoadBatchEvent> + 'a {
+        let requests = stream::unfold(queue, |queue| async move {
+            let request = queue.next().await;
+            Some((request, queue))
         });
 
         // Main flow, for each image, fetch the image bytes from the cdn
         // logging any failures along the way
-        let fetch_image_futures = stream::iter(curated_items).map(
-            move |(content_set_title, item_image_url)| async move {
-                self.load_tile_image_bytes(&item_image_url)
-                    .await
-                    .map(|image_bytes| ImageLoadEvent {
-                        img_url: item_image_url.clone(),
-                        bytes: image_bytes,
-                        content_set_title: content_set_title.clone(),
-                    })
-                    .map(Some)
-                    .unwrap_or_else(|e| {
-                        warn!("Failed fetching image url {:?}", e);
-                        None
-                    })
-            },
-        );
+        let fetch_image_futures = requests.map(move |request| async move {
+            let TileImageRequest { img_url, tiles } = request;
+            let event = self
+                .load_image_bytes(&img_url)
+                .await
+                .map(|image_bytes| ImageLoadEvent {
+                    img_url: img_url.clone(),
+                    bytes: image_bytes,
+                    tiles,
+                })
+                .map_err(|e| warn!("Failed fetching image url {:?}", e))
+                .ok();
+            queue.done();
+            event
+        });
 
-        // Taking our stream, run it concurrently (buffered)
-        // and then group together 15 images at a time or until the stream is finished
+        // Taking our stream, run it concurrently, in whatever order the
+        // images finish, and then group together up to 15 images that are ready
         // sending batches of images to the UI is faster than
         // sending individual images to the ui
         fetch_image_futures
-            .buffered(self.concurrency)
+            .buffer_unordered(self.concurrency)
             .ready_chunks(15)
             .map(|img_load_events| ImageLoadBatchEvent {
                 events: img_load_events.into_iter().flatten().collect(),
             })
     }
 
-    /// Loads home page content sets
+    /// Streams home page content sets in order,
+    /// each one is yielded as soon as it is available
     ///
     /// When requesting the home page, some of the
     /// content sets are returned as "refs", and
     /// "refs" do not have any images in them.
     ///
-    /// Load any missing content sets
-    pub async fn load_home_content_sets(&self) -> Result<Vec<ContentSet>> {
-        // load the home screen
-        let home_screen = self.load_home_screen().await?;
+    /// Load any missing content sets while the rest
+    /// of the home screen is still arriving
+    ///
+    /// From a source without pages each set is whole,
+    /// so shelves do not ask for pages that would fail
+    pub async fn stream_home_content_sets(
+        &self,
+    ) -> Result<impl Stream<Item = Result<ContentSet>> + '_> {
+        let containers = self.stream_home_screen().await?;
 
         // We want to maintain order of the content sets, so all will flow
         // through the loader, the ones that exist already will go through completed
-        let fetched_content_sets = stream::iter(home_screen.content_sets())
-            .map(|cs| async {
-                if let Some(ref_id) = cs.ref_id() {
+        Ok(containers
+            .map(move |container| async move {
+                let cs = container?.content_set();
+                let set = if let Some(ref_id) = cs.ref_id() {
                     // Note: sometimes the title coming back
                     // changes, GordonRamsey is the example
                     // here, we reset the title after load to be sure
                     self.load_set_ref(ref_id)
                         .await
                         .context("Loading content set")
-                        .map(|set| set.set_title(cs.title()))
+                        .map(|set| set.set_title(cs.title()).with_style(cs.style().cloned()))?
                 } else {
-                    Ok(cs)
+                    cs
+                };
+                if self.serves_pages() {
+                    Ok(set)
+                } else {
+                    Ok(set.without_paging())
                 }
             })
-            .buffered(self.concurrency) // runs n futures in parallel
-            .collect::<Vec<Result<ContentSet>>>()
-            .await;
-
-        let content_sets_without_failures: Vec<ContentSet> = fetched_content_sets
-            .into_iter()
-            .inspect(|result| {
-                if let Err(e) = result {
-                    error!("Failure loading content set: {:?}", e);
-                }
+            .buffered(self.concurrency)) // runs n futures in parallel
+    }
+
+    /// Streams the containers of the home screen,
+    /// parsing each one as soon as its bytes arrive
+    async fn stream_home_screen(&self) -> Result<impl Stream<Item = Result<Container>>> {
+        // only the initial request is retried, once containers
+        // have been handed out the document cannot be restarted
+        let chunks = self
+            .with_retry("home screen", || {
+                self.source.fetch_stream(&self.http, "home.json")
             })
-            .filter_map(|r| r.ok())
-            .collect();
+            .await
+            .context("Fetching home screen")?;
 
-        Ok(content_sets_without_failures)
+        // feed chunks into the parser until it has a container for us,
+        // the chunk stream is dropped once the document is complete
+        let containers = stream::unfold(
+            (Some(chunks), ContainerParser::new()),
+            |(mut chunks, mut parser)| async move {
+                loop {
+                    if let Some(container) = parser.next_container() {
+                        return Some((container, (chunks, parser)));
+                    }
+                    match chunks.as_mut()?.next().await {
+                        Some(Ok(chunk)) => parser.feed(&chunk),
+                        Some(Err(e)) => {
+                            parser.fail(e.context("Reading home screen"));
+                            chunks = None;
+                        }
+                        None => {
+                            parser.finish();
+                            chunks = None;
+                        }
+                    }
+                }
+            },
+        );
+        Ok(containers)
     }
 
-    async fn load_home_screen(&self) -> Result<HomeScreen> {
-        // Load the raw response from the cdn
-        let response = self
-            .http
-            .get("https://cd-static.bamgrid.com/dp-117731241344/home.json")
-            .send()
-            .await?;
+    /// Loads a further page of a content set, starting at the offset,
+    /// which is a whole number of pages in, see [`set_page_path`]
+    pub async fn load_set_page(
+        &self,
+        set_id: &str,
+        offset: usize,
+        page_size: usize,
+    ) -> Result<ContentSet> {
+        if !self.source.serves_pages() {
+            return Err(anyhow!(
+                "Content source does not serve pages of set {}",
+                set_id
+            ));
+        }
+        let path = set_page_path(set_id, offset, page_size);
+        let data = self
+            .with_retry(&path, || self.source.fetch(&self.http, &path))
+            .await
+            .context("Fetching set page from content source")?;
 
-        // parse the bytes out of the response
-        let data = response.bytes().await?;
+        let set_ref: SetRef = serde_json::from_slice(&data)
+            .context(format!("Deserializing set {} page at {}", set_id, offset))?;
 
-        // deserialize the home Home, this has an added "data" element
-        let root: HomeRoot = serde_json::from_slice(&data)?;
-        Ok(root.home_screen())
+        let (content_set, warnings) = set_ref.content_set()?;
+        log_set_warnings(set_id, &warnings);
+        Ok(content_set)
     }
 
     async fn load_set_ref(&self, ref_id: &String) -> Result<ContentSet> {
-        let url = format!(
-            "https://cd-static.bamgrid.com/dp-117731241344/sets/{}.json",
-            ref_id
-        );
-        let response = self
-            .http
-            .get(url)
-            .send()
+        let path = format!("sets/{}.json", ref_id);
+        let data = self
+            .with_retry(&path, || self.source.fetch(&self.http, &path))
             .await
-            .context("Fetching ref from url")?;
+            .context("Fetching ref from content source")?;
+
+        let set_ref: SetRef =
+            serde_json::from_slice(&data).context(format!("Deserializing set ref {}", ref_id))?;
 
-        let data = response
-            .bytes()
+        let (content_set, warnings) = set_ref.content_set()?;
+        log_set_warnings(ref_id, &warnings);
+        debug!(
+            "Loaded set ref {} type = {:?} content class = {:?}",
+            ref_id,
+            content_set.set_type(),
+            content_set.content_class()
+        );
+        Ok(content_set)
+    }
+
+    /// Loads a list of images concurrently, e.g. to prefetch art before it
+    /// is needed. Images are yielded as they arrive, with their urls, and
+    /// failures are logged and skipped
+    pub fn stream_images(&self, img_urls: Vec<String>) -> impl Stream<Item = (String, Bytes)> + '_ {
+        self.stream_image_results(img_urls)
+            .filter_map(|(img_url, bytes)| future::ready(bytes.map(|bytes| (img_url, bytes))))
+    }
+
+    /// Like [`DisneyService::stream_images`], but failures are
+    /// yielded too, as None, for callers waiting on every image
+    pub fn stream_image_results(
+        &self,
+        img_urls: Vec<String>,
+    ) -> impl Stream<Item = (String, Option<Bytes>)> + '_ {
+        stream::iter(img_urls)
+            .map(move |img_url| async move {
+                let bytes = self
+                    .load_image_bytes(&img_url)
+                    .await
+                    .map_err(|e| warn!("Failed fetching image {:?}", e))
+                    .ok();
+                (img_url, bytes)
+            })
+            .buffer_unordered(self.concurrency)
+    }
+
+    /// Loads image bytes, from the image cache if
+    /// we have them, otherwise from the cdn
+    ///
+    /// Requests for an image already being loaded share that load
+    pub async fn load_image_bytes(&self, img_url: &String) -> Result<Bytes> {
+        self.images_in_flight
+            .load(img_url, || self.fetch_image_bytes(img_url))
             .await
-            .context("Fetching bytes from response")?;
+    }
 
-        let set_ref: SetRef = serde_json::from_slice(&data)
-            .context(format!("Deserializing curated set ref {}", ref_id))?;
+    async fn fetch_image_bytes(&self, img_url: &String) -> Result<Bytes> {
+        let image_cache = match &self.image_cache {
+            Some(image_cache) => Arc::clone(image_cache),
+            None => {
+                return self
+                    .with_retry(img_url, || self.download_image_bytes(img_url))
+                    .await
+            }
+        };
+
+        // the cache does blocking file io, keep it off of the runtime threads
+        let url = img_url.clone();
+        let cache = Arc::clone(&image_cache);
+        if let Some(bytes) = task::spawn_blocking(move || cache.get(&url)).await? {
+            debug!("Image cache hit {}", img_url);
+            return Ok(bytes);
+        }
 
-        set_ref
-            .content_set()
-            .ok_or_else(|| anyhow!("Unable to find a curated set"))
+        let bytes = self
+            .with_retry(img_url, || self.download_image_bytes(img_url))
+            .await?;
+        let url = img_url.clone();
+        let body = bytes.clone();
+        if let Err(e) = task::spawn_blocking(move || image_cache.put(&url, &body)).await? {
+            warn!("Failed caching image {:?}", e);
+        }
+        Ok(bytes)
     }
 
-    async fn load_tile_image_bytes(&self, img_url: &String) -> Result<Bytes> {
-        let response = reqwest::get(img_url).await?;
+    async fn download_image_bytes(&self, img_url: &String) -> Result<Bytes> {
+        let response = self.http.get(img_url).send().await?;
         if response.status() == StatusCode::OK {
             response
                 .bytes()
                 .await
                 .context("Retreiving bytes from response")
         } else {
-            Err(anyhow::anyhow!(
-                "Unable to find image data: invalid image response code {:?}",
-                response.status()
-            ))
+            Err(Error::new(StatusError(response.status()))
+                .context("Unable to find image data: invalid image response"))
+        }
+    }
+
+    /// Runs the request, retrying failures that may succeed
+    /// on another attempt according to the retry policy
+    async fn with_retry<T, F, Fut>(&self, what: &str, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 1;
+        loop {
+            self.retry_stats.record_request();
+            match request().await {
+                Ok(value) => return Ok(value),
+                Err(e)
+                    if attempt < self.retry_policy.max_attempts
+                        && self.retry_policy.is_retryable(&e) =>
+                {
+                    let delay = self.retry_policy.backoff(attempt);
+                    warn!(
+                        "Retrying {} in {} millis, attempt {} of {} failed: {:?}",
+                        what,
+                        delay.as_millis(),
+                        attempt,
+                        self.retry_policy.max_attempts,
+                        e
+                    );
+                    self.retry_stats.record_retry();
+                    time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    self.retry_stats.record_failure();
+                    return Err(e);
+                }
+            }
         }
     }
 }
+
+/// The path of a page of a content set
+///
+/// The demo cdn only serves whole set refs, it has no paging. Pages are
+/// numbered from 1 with a page size, the way the Disney+ content api pages
+/// sets (`.../setId/{set_id}/pageSize/{page_size}/page/{page}`), at a path
+/// that is also a file name so a directory or bundle can serve them
+pub fn set_page_path(set_id: &str, offset: usize, page_size: usize) -> String {
+    let page = offset / page_size.max(1) + 1;
+    format!("sets/{}/pageSize/{}/page/{}.json", set_id, page_size, page)
+}
+
+/// Set ref problems that did not stop the set
+/// from loading are logged rather than failing
+fn log_set_warnings(id: &str, warnings: &[SetRefWarning]) {
+    for warning in warnings {
+        warn!("Set {}: {}", id, warning);
+    }
+}
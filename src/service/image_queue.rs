@@ -0,0 +1,218 @@
+//! Tile images waiting to load, most important first
+//!
+//! Images are queued as each content set or page loads, but are not
+//! loaded in that order. The ui tells the queue what is on screen with a
+//! [`Viewport`] whenever it moves, and the next image to load is always
+//! the one closest to the user's attention right then:
+//!
+//! 1. tiles on screen
+//! 2. tiles a few key presses away, along the shelves on screen
+//!    and the shelves just above and below
+//! 3. everything else, nearest the focus first
+//!
+//! So work the user has scrolled away from drops back behind the
+//! tiles they are looking at, and loads once those are done
+use log::warn;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+use tokio::sync::Notify;
+
+use crate::event::TileRef;
+use crate::model::home::ContentSet;
+
+/// Tiles past either end of the visible ones that load
+/// ahead, about a page of the shelf
+const AHEAD: usize = 6;
+
+/// How much further away a tile is for each shelf between it and
+/// the focus than for each tile along, shelves are far apart
+const SHELF_DISTANCE: usize = 1000;
+
+/// An image to load, for every tile in its content set that shows it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TileImageRequest {
+    pub img_url: String,
+    pub tiles: Vec<TileRef>,
+}
+impl TileImageRequest {
+    /// The images for the tiles of a content set, whose first tile is
+    /// `first`. Items sharing an image share the one request
+    pub fn for_content_set(
+        first: TileRef,
+        content_set: &ContentSet,
+        image_size: &str,
+    ) -> Vec<TileImageRequest> {
+        let sizes = content_set.tile_shape().image_sizes(image_size);
+        let mut requests: Vec<TileImageRequest> = Vec::new();
+        let mut by_url: HashMap<&String, usize> = HashMap::new();
+        for (index, item) in content_set.items_iter().enumerate() {
+            let tile = TileRef {
+                shelf: first.shelf,
+                tile: first.tile + index,
+            };
+            match item.tile_image_url(&sizes) {
+                Some(img_url) => match by_url.get(img_url) {
+                    Some(existing) => requests[*existing].tiles.push(tile),
+                    None => {
+                        by_url.insert(img_url, requests.len());
+                        requests.push(TileImageRequest {
+                            img_url: img_url.clone(),
+                            tiles: vec![tile],
+                        });
+                    }
+                },
+                None => warn!("No image found for sizes {:?} and item {:?}", sizes, item),
+            }
+        }
+        requests
+    }
+}
+
+/// What the user can see, sent by the ui whenever it changes
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Viewport {
+    /// The focused tile, or the selected tile of the first
+    /// shelf while the hero is focused
+    pub focus: Option<TileRef>,
+    /// The tiles on screen, for each shelf on screen
+    pub visible: Vec<(usize, Range<usize>)>,
+}
+impl Viewport {
+    /// Lower is sooner, by how visible the tile is and then
+    /// how far it is from the focus
+    fn rank(&self, tile: TileRef) -> (u8, usize) {
+        let distance = match self.focus {
+            Some(focus) => {
+                focus.shelf.abs_diff(tile.shelf) * SHELF_DISTANCE + focus.tile.abs_diff(tile.tile)
+            }
+            None => tile.shelf * SHELF_DISTANCE + tile.tile,
+        };
+        (self.class(tile), distance)
+    }
+
+    fn class(&self, tile: TileRef) -> u8 {
+        let on_shelf = self
+            .visible
+            .iter()
+            .find(|(shelf, _)| *shelf == tile.shelf)
+            .map(|(_, tiles)| tiles.clone());
+        if on_shelf
+            .as_ref()
+            .is_some_and(|tiles| tiles.contains(&tile.tile))
+        {
+            return 0;
+        }
+
+        // shelves just off screen have not scrolled
+        // since they were last seen, from the start
+        let first = self.visible.iter().map(|(shelf, _)| *shelf).min();
+        let last = self.visible.iter().map(|(shelf, _)| *shelf).max();
+        let near_shelf = match (first, last) {
+            (Some(first), Some(last)) => tile.shelf + 1 >= first && tile.shelf <= last + 1,
+            _ => false,
+        };
+        let tiles = on_shelf.unwrap_or(0..0);
+        let near_tile = tile.tile + AHEAD >= tiles.start && tile.tile < tiles.end + AHEAD;
+        if near_shelf && near_tile {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    pending: Vec<TileImageRequest>,
+    viewport: Viewport,
+    // taken by a loader and not yet done
+    loading: usize,
+}
+
+/// Tile images waiting to load, shared by the ui, which queues
+/// images and moves the viewport, and the loader, which takes
+/// the most important image whenever it can load another
+#[derive(Default)]
+pub struct ImageQueue {
+    state: Mutex<QueueState>,
+    changed: Notify,
+}
+impl ImageQueue {
+    /// Queues images to load, an image already waiting for another
+    /// shelf gains the new tiles rather than loading twice
+    pub fn push(&self, requests: Vec<TileImageRequest>) {
+        if requests.is_empty() {
+            return;
+        }
+        let mut state = self.lock();
+        for request in requests {
+            let waiting = state
+                .pending
+                .iter_mut()
+                .find(|pending| pending.img_url == request.img_url);
+            match waiting {
+                Some(waiting) => {
+                    for tile in request.tiles {
+                        if !waiting.tiles.contains(&tile) {
+                            waiting.tiles.push(tile);
+                        }
+                    }
+                }
+                None => state.pending.push(request),
+            }
+        }
+        drop(state);
+        self.changed.notify_one();
+    }
+
+    /// Reorders the waiting images for what is on screen now
+    pub fn set_viewport(&self, viewport: Viewport) {
+        self.lock().viewport = viewport;
+    }
+
+    /// Takes the most important image, waiting for one to be queued,
+    /// [`ImageQueue::done`] is called once it has loaded
+    pub async fn next(&self) -> TileImageRequest {
+        loop {
+            if let Some(request) = self.take() {
+                return request;
+            }
+            self.changed.notified().await;
+        }
+    }
+
+    fn take(&self) -> Option<TileImageRequest> {
+        let mut state = self.lock();
+        let viewport = &state.viewport;
+        let (index, _) = state
+            .pending
+            .iter()
+            .enumerate()
+            .map(|(index, request)| {
+                let rank = request.tiles.iter().map(|tile| viewport.rank(*tile)).min();
+                (index, rank)
+            })
+            .min_by_key(|(_, rank)| *rank)?;
+        state.loading += 1;
+        Some(state.pending.remove(index))
+    }
+
+    /// An image taken with [`ImageQueue::next`] has loaded, or failed
+    pub fn done(&self) {
+        let mut state = self.lock();
+        state.loading = state.loading.saturating_sub(1);
+    }
+
+    /// Nothing waiting and nothing loading
+    pub fn is_idle(&self) -> bool {
+        let state = self.lock();
+        state.pending.is_empty() && state.loading == 0
+    }
+
+    fn lock(&self) -> MutexGuard<'_, QueueState> {
+        // the state is always left consistent, so a panic
+        // elsewhere while holding the lock is not a problem
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -0,0 +1,111 @@
+//! Sharing one image download between everyone asking for it at once
+//!
+//! The same program often appears on several shelves, so its tile image
+//! can be requested again while the first request is still going. Later
+//! requests wait for the first one and get the same bytes, the ui then
+//! decodes them once, as textures are cached by url
+use anyhow::{anyhow, Error, Result};
+use bytes::Bytes;
+use futures::channel::oneshot;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+type Waiter = oneshot::Sender<std::result::Result<Bytes, String>>;
+
+/// Image requests in flight by url, with counts of
+/// requests that shared one and requests that started one
+#[derive(Debug, Default)]
+pub struct InFlight {
+    // the requests waiting on each url, the first request for a url
+    // does the fetch and is not in the list
+    waiting: Mutex<HashMap<String, Vec<Waiter>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+impl InFlight {
+    /// Runs `fetch` for the url, unless the url is already being fetched,
+    /// in which case this waits for that fetch and returns its result
+    pub async fn load<F, Fut>(&self, url: &str, fetch: F) -> Result<Bytes>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Bytes>>,
+    {
+        let shared = {
+            let mut waiting = self.lock();
+            match waiting.get_mut(url) {
+                Some(waiters) => {
+                    let (sender, receiver) = oneshot::channel();
+                    waiters.push(sender);
+                    Some(receiver)
+                }
+                None => {
+                    waiting.insert(url.to_string(), Vec::new());
+                    None
+                }
+            }
+        };
+
+        if let Some(receiver) = shared {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return receiver
+                .await
+                .map_err(|_| anyhow!("Request for image {} was cancelled", url))?
+                .map_err(Error::msg);
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let mut leader = Leader {
+            in_flight: self,
+            url,
+            finished: false,
+        };
+        let result = fetch().await;
+        for waiter in leader.finish() {
+            // the waiter may have given up, that is fine
+            let _ = waiter.send(match &result {
+                Ok(bytes) => Ok(bytes.clone()),
+                Err(e) => Err(format!("{:#}", e)),
+            });
+        }
+        result
+    }
+
+    /// Requests that shared a fetch already in flight
+    pub fn hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Requests that started a fetch
+    pub fn misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Vec<Waiter>>> {
+        // entries are only inserted and removed whole, so
+        // a panic while holding the lock is not a problem
+        self.waiting.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The request doing the fetch for a url, if it is dropped part way
+/// the url is no longer in flight and the waiters get an error
+struct Leader<'a> {
+    in_flight: &'a InFlight,
+    url: &'a str,
+    finished: bool,
+}
+impl Leader<'_> {
+    fn finish(&mut self) -> Vec<Waiter> {
+        self.finished = true;
+        self.in_flight.lock().remove(self.url).unwrap_or_default()
+    }
+}
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.in_flight.lock().remove(self.url);
+        }
+    }
+}
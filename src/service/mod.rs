@@ -1 +1,7 @@
+//! Loading content and images, over http or from local fixtures
+pub mod cache;
 pub mod disney;
+pub mod image_queue;
+pub mod in_flight;
+pub mod retry;
+pub mod source;
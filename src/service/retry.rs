@@ -0,0 +1,124 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::Error;
+use rand::Rng;
+use reqwest::StatusCode;
+
+use crate::config::Config;
+
+/// How service calls are retried, applied to every http
+/// request made by the [`crate::service::disney::DisneyService`]
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    /// The delay before the first retry, doubled for each retry after
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub connect_timeout: Duration,
+    /// Timeout for an entire request, including reading the body
+    pub request_timeout: Duration,
+}
+impl Default for RetryPolicy {
+    /// The policy from the default config, see [`Config::retry_policy`]
+    fn default() -> Self {
+        Config::default().retry_policy()
+    }
+}
+impl RetryPolicy {
+    /// The delay before the next attempt, after `attempt` attempts have failed
+    ///
+    /// Exponential backoff with "full jitter", a random delay up to the
+    /// backoff, so that many failed requests do not retry in lock step
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let cap = exponential.min(self.max_delay);
+        cap.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    /// Only failures that could succeed on a second try are retried,
+    /// timeouts, dropped connections, and 408, 429 or 5xx statuses
+    pub fn is_retryable(&self, error: &Error) -> bool {
+        error.chain().any(|cause| {
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                e.is_timeout()
+                    || e.is_connect()
+                    || is_dropped_connection(e)
+                    || e.is_body()
+                    || e.status().is_some_and(is_retryable_status)
+            } else if let Some(StatusError(status)) = cause.downcast_ref::<StatusError>() {
+                is_retryable_status(*status)
+            } else {
+                false
+            }
+        })
+    }
+}
+
+/// A request that failed part way, e.g. the connection was reset, rather
+/// than one that could never be sent, such as one with an invalid url
+fn is_dropped_connection(error: &reqwest::Error) -> bool {
+    if !error.is_request() || error.is_builder() {
+        return false;
+    }
+    let mut source = std::error::Error::source(error);
+    while let Some(cause) = source {
+        if cause.is::<std::io::Error>() {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// A response with an unexpected status code
+#[derive(Debug)]
+pub struct StatusError(pub StatusCode);
+impl std::fmt::Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "invalid response code {:?}", self.0)
+    }
+}
+impl std::error::Error for StatusError {}
+
+/// Counters for service calls, logged when loading completes
+#[derive(Debug, Default)]
+pub struct RetryStats {
+    requests: AtomicU64,
+    retries: AtomicU64,
+    failures: AtomicU64,
+}
+impl RetryStats {
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_retry(&self) {
+        self.retries.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_failure(&self) {
+        self.failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> u64 {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.failures.load(Ordering::Relaxed)
+    }
+}
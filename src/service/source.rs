@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context, Error, Result};
+use bytes::Bytes;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use reqwest::Client;
+use serde_json::Value;
+
+/// The default location of the home screen and set ref json
+pub const DEFAULT_BASE_URL: &str = "https://cd-static.bamgrid.com/dp-117731241344";
+
+/// Where the home screen and set ref json documents come from
+///
+/// Documents are addressed by a relative path, `home.json` for
+/// the home screen and `sets/{ref_id}.json` for set refs, so each
+/// source only has to know how to resolve a path
+pub enum ContentSource {
+    /// Fetch documents from the cdn
+    Network { base_url: String },
+
+    /// Read documents from a local directory of json fixtures
+    ///
+    /// Set refs are read from `sets/{ref_id}.json`, a set ref without
+    /// a fixture fails to load as it would over the network, rather
+    /// than every ref showing the same content. Further pages of a set
+    /// are read from `sets/{set_id}/pageSize/{page_size}/page/{page}.json`,
+    /// see [`set_page_path`]
+    ///
+    /// [`set_page_path`]: crate::service::disney::set_page_path
+    Directory(PathBuf),
+
+    /// A recorded bundle, a single json file whose top level object
+    /// maps each document path to the document itself, e.g.
+    /// `{"home.json": {...}, "sets/{ref_id}.json": {...}}`
+    Bundle(HashMap<String, Value>),
+}
+impl ContentSource {
+    /// Parses a content source from a string
+    ///
+    /// - `network` or `network:{base_url}`
+    /// - `dir:{path}`
+    /// - `bundle:{path}`
+    pub fn parse(value: &str) -> Result<ContentSource> {
+        match value.split_once(':') {
+            None if value == "network" => Ok(ContentSource::network(DEFAULT_BASE_URL)),
+            Some(("network", base_url)) => Ok(ContentSource::network(base_url)),
+            Some(("dir", path)) => Ok(ContentSource::Directory(PathBuf::from(path))),
+            Some(("bundle", path)) => ContentSource::load_bundle(Path::new(path)),
+            _ => Err(anyhow!(
+                "Invalid content source {:?}, expected network, dir:<path> or bundle:<path>",
+                value
+            )),
+        }
+    }
+
+    pub fn network(base_url: &str) -> ContentSource {
+        ContentSource::Network {
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Loads a recorded bundle from disk
+    pub fn load_bundle(path: &Path) -> Result<ContentSource> {
+        let data = std::fs::read(path).context(format!("Reading bundle {:?}", path))?;
+        let documents: HashMap<String, Value> =
+            serde_json::from_slice(&data).context(format!("Deserializing bundle {:?}", path))?;
+        Ok(ContentSource::Bundle(documents))
+    }
+
+    /// Whether further pages of a set can be fetched, the demo cdn
+    /// only serves whole set refs, see [`set_page_path`]
+    ///
+    /// [`set_page_path`]: crate::service::disney::set_page_path
+    pub fn serves_pages(&self) -> bool {
+        !matches!(self, ContentSource::Network { .. })
+    }
+
+    /// Fetches the raw bytes of the document at the relative path
+    pub async fn fetch(&self, http: &Client, path: &str) -> Result<Bytes> {
+        match self {
+            ContentSource::Network { base_url } => {
+                let url = format!("{}/{}", base_url, path);
+                let response = http
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .context(format!("Fetching {}", url))?;
+                response
+                    .bytes()
+                    .await
+                    .context("Fetching bytes from response")
+            }
+            ContentSource::Directory(dir) => {
+                let file = dir.join(path);
+                if !file.exists() {
+                    return Err(anyhow!("No fixture for {} in {:?}", path, dir));
+                }
+                let data = tokio::fs::read(&file)
+                    .await
+                    .context(format!("Reading {:?}", file))?;
+                Ok(Bytes::from(data))
+            }
+            ContentSource::Bundle(documents) => {
+                let document = documents
+                    .get(path)
+                    .ok_or_else(|| anyhow!("Bundle is missing document {}", path))?;
+                Ok(Bytes::from(serde_json::to_vec(document)?))
+            }
+        }
+    }
+
+    /// Fetches the document at the relative path as a stream of chunks,
+    /// over the network the chunks are yielded as they arrive
+    pub async fn fetch_stream(
+        &self,
+        http: &Client,
+        path: &str,
+    ) -> Result<BoxStream<'static, Result<Bytes>>> {
+        match self {
+            ContentSource::Network { base_url } => {
+                let url = format!("{}/{}", base_url, path);
+                let response = http
+                    .get(&url)
+                    .send()
+                    .await
+                    .and_then(|r| r.error_for_status())
+                    .context(format!("Fetching {}", url))?;
+                Ok(response.bytes_stream().map_err(Error::from).boxed())
+            }
+            _ => {
+                let data = self.fetch(http, path).await?;
+                Ok(stream::once(async { Ok(data) }).boxed())
+            }
+        }
+    }
+}
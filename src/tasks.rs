@@ -0,0 +1,72 @@
+//! Background work owned by whoever started it
+//!
+//! Loading runs on tokio tasks. Rather than being left detached, each
+//! task is spawned into a [`Tasks`] group owned by the app or by a screen,
+//! which cancels them when the screen closes and waits for them on exit
+use futures::future;
+use std::future::Future;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+/// A group of background tasks, cancelled together
+///
+/// Cancelling a task stops it at its next await, so a download in
+/// progress is dropped rather than finished. Dropping the group
+/// cancels its tasks
+#[derive(Default)]
+pub struct Tasks {
+    token: CancellationToken,
+    handles: Vec<JoinHandle<()>>,
+}
+impl Tasks {
+    pub fn new() -> Tasks {
+        Tasks::default()
+    }
+
+    /// Runs a task in the background until it finishes or the group is cancelled
+    pub fn spawn(&mut self, task: impl Future<Output = ()> + Send + 'static) {
+        self.handles.retain(|handle| !handle.is_finished());
+        let token = self.token.clone();
+        self.handles.push(tokio::spawn(async move {
+            tokio::select! {
+                _ = token.cancelled() => {}
+                _ = task => {}
+            }
+        }));
+    }
+
+    /// How many tasks are still running
+    pub fn len(&self) -> usize {
+        self.handles
+            .iter()
+            .filter(|handle| !handle.is_finished())
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Cancels every task, the group can be used again for new tasks
+    pub fn cancel(&mut self) {
+        self.token.cancel();
+        self.token = CancellationToken::new();
+        self.handles.clear();
+    }
+
+    /// Cancels every task and waits for them to stop, for no longer
+    /// than `timeout`. False if some were still running
+    pub async fn shutdown(&mut self, timeout: Duration) -> bool {
+        self.token.cancel();
+        let handles = std::mem::take(&mut self.handles);
+        tokio::time::timeout(timeout, future::join_all(handles))
+            .await
+            .is_ok()
+    }
+}
+impl Drop for Tasks {
+    fn drop(&mut self) {
+        self.token.cancel();
+    }
+}
@@ -0,0 +1,155 @@
+//! Time based animations for the ui
+//!
+//! Anything that moves, the shelves scrolling or a tile growing as it
+//! is selected, is a [`Tween`] towards where it is going. Tweens are
+//! advanced by the event loop each frame, see [`HomePage::tick`]
+//!
+//! [`HomePage::tick`]: crate::ui::home_page::HomePage::tick
+use crate::config::Config;
+
+/// How an animation moves from its start to its end
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    /// Starts fast and slows into place
+    EaseOut,
+    /// Speeds up and then slows into place
+    EaseInOut,
+}
+impl Easing {
+    /// Every easing, by the name used in the config
+    const NAMES: &'static [(&'static str, Easing)] = &[
+        ("linear", Easing::Linear),
+        ("ease_out", Easing::EaseOut),
+        ("ease_in_out", Easing::EaseInOut),
+    ];
+
+    pub fn from_name(name: &str) -> Option<Easing> {
+        Easing::NAMES
+            .iter()
+            .find(|(easing_name, _)| *easing_name == name)
+            .map(|(_, easing)| *easing)
+    }
+
+    pub fn names() -> Vec<&'static str> {
+        Easing::NAMES.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// How far along the animation is, from 0 to 1,
+    /// `t` of the way through its duration
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut if t < 0.5 => 4.0 * t * t * t,
+            Easing::EaseInOut => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+        }
+    }
+}
+
+/// How long each kind of animation takes, in seconds
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Timing {
+    /// Shelves scrolling to the selection, and hero slides sliding in
+    pub scroll: f32,
+    /// Tiles growing or shrinking as the selection moves
+    pub focus: f32,
+    pub easing: Easing,
+    /// Between the hero carousel moving on by itself, 0 never moves
+    pub hero_interval: f32,
+    /// The background crossfading to the focused program's art
+    pub fade: f32,
+}
+impl Timing {
+    pub fn new(config: &Config) -> Timing {
+        Timing {
+            scroll: config.scroll_animation_ms as f32 / 1000.0,
+            focus: config.focus_animation_ms as f32 / 1000.0,
+            easing: config.animation_easing(),
+            hero_interval: config.hero_interval_ms as f32 / 1000.0,
+            fade: config.fade_animation_ms as f32 / 1000.0,
+        }
+    }
+
+    /// Nothing animates, everything jumps to where it is going,
+    /// and the hero carousel only moves when asked
+    pub fn instant() -> Timing {
+        Timing {
+            scroll: 0.0,
+            focus: 0.0,
+            easing: Easing::Linear,
+            hero_interval: 0.0,
+            fade: 0.0,
+        }
+    }
+}
+
+/// A value moving towards a target over time
+///
+/// Moving to a new target part way through starts from
+/// wherever the value is, so animations can be interrupted
+/// by the next key press without jumping
+#[derive(Clone, Copy, Debug)]
+pub struct Tween {
+    from: f32,
+    to: f32,
+    elapsed: f32,
+    duration: f32,
+    easing: Easing,
+}
+impl Tween {
+    /// A tween at rest at `value`
+    pub fn new(value: f32) -> Tween {
+        Tween {
+            from: value,
+            to: value,
+            elapsed: 0.0,
+            duration: 0.0,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// The value right now
+    pub fn value(&self) -> f32 {
+        if self.is_active() {
+            let progress = self.easing.apply(self.elapsed / self.duration);
+            self.from + (self.to - self.from) * progress
+        } else {
+            self.to
+        }
+    }
+
+    /// Where the value is going, or is if it has arrived
+    pub fn target(&self) -> f32 {
+        self.to
+    }
+
+    /// Starts moving towards `to` from the current value,
+    /// a duration of 0 jumps straight there
+    pub fn animate_to(&mut self, to: f32, seconds: f32, easing: Easing) {
+        self.from = self.value();
+        self.to = to;
+        self.elapsed = 0.0;
+        self.duration = seconds.max(0.0);
+        self.easing = easing;
+    }
+
+    /// Stops at `value` without animating
+    pub fn jump_to(&mut self, value: f32) {
+        *self = Tween::new(value);
+    }
+
+    /// Stops wherever the value is right now
+    pub fn stop(&mut self) {
+        self.jump_to(self.value());
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.elapsed < self.duration
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        self.elapsed = (self.elapsed + seconds).min(self.duration);
+    }
+}
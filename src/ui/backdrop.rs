@@ -0,0 +1,232 @@
+use crate::event::BackgroundImageLoadEvent;
+use crate::model::home::ContentSetItem;
+use crate::ui::animation::{Timing, Tween};
+use crate::ui::render::report;
+use crate::ui::texture_cache::TextureCache;
+use crate::ui::tile::crop_to_fit;
+use bytes::Bytes;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, TextureQuery};
+use std::collections::HashSet;
+
+/// The background art variant, the shape of the window
+const BACKGROUND_SIZE: &str = "1.78";
+
+/// The most background images kept in memory, enough for the
+/// focused program and the ones a key press away from it
+const MAX_IMAGES: usize = 16;
+
+/// How dark the background is at the top of the screen, and at the
+/// bottom, so the shelves and their labels stay readable over it
+const SHADE_TOP: f32 = 120.0;
+const SHADE_BOTTOM: f32 = 230.0;
+
+/// Bands in the darkening gradient
+const SHADE_BANDS: i32 = 24;
+
+/// The focused program's background art filling the window
+/// behind the home page, crossfading as the focus moves
+///
+/// The art for the programs next to the focus is loaded ahead of
+/// time, so moving the focus along a shelf fades straight away
+pub struct Backdrop {
+    timing: Timing,
+    // loaded art by url, the least recently shown first
+    images: Vec<(String, Bytes)>,
+    // asked for, loaded or not, and not yet taken by the event loop
+    requested: HashSet<String>,
+    pending: Vec<String>,
+    // the focused program's art, shown once it has loaded
+    wanted: Option<String>,
+    // the art fading in over the art that was there before
+    shown: Option<String>,
+    previous: Option<String>,
+    fade: Tween,
+}
+impl Backdrop {
+    pub fn new(timing: Timing) -> Backdrop {
+        Backdrop {
+            timing,
+            images: Vec::new(),
+            requested: HashSet::new(),
+            pending: Vec::new(),
+            wanted: None,
+            shown: None,
+            previous: None,
+            fade: Tween::new(1.0),
+        }
+    }
+
+    /// The background art for a program, if it has any
+    pub fn image_url(item: &ContentSetItem) -> Option<&String> {
+        item.background_image_url(BACKGROUND_SIZE)
+    }
+
+    /// Fades to the focused program's art, or to black when it has
+    /// none, once it has loaded. The neighbours' art is loaded too
+    pub fn follow(&mut self, wanted: Option<String>, neighbours: Vec<String>) {
+        for url in wanted.iter().chain(neighbours.iter()) {
+            if self.requested.insert(url.clone()) {
+                self.pending.push(url.clone());
+            }
+        }
+
+        if wanted != self.wanted {
+            self.wanted = wanted;
+            if self.wanted.is_none() || self.loaded(self.wanted.as_ref()) {
+                self.crossfade();
+            }
+        }
+    }
+
+    /// The art to load since the last call, see [`Backdrop::on_image_load`]
+    pub fn take_requests(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn on_image_load(&mut self, event: BackgroundImageLoadEvent) {
+        let url = event.img_url;
+        if !self.loaded(Some(&url)) {
+            self.images.push((url.clone(), event.bytes));
+            self.evict();
+        }
+        if self.wanted.as_ref() == Some(&url) {
+            self.crossfade();
+        }
+    }
+
+    fn loaded(&self, url: Option<&String>) -> bool {
+        url.is_some_and(|url| self.images.iter().any(|(loaded, _)| loaded == url))
+    }
+
+    fn bytes(&self, url: &str) -> Option<&Bytes> {
+        self.images
+            .iter()
+            .find(|(loaded, _)| loaded == url)
+            .map(|(_, bytes)| bytes)
+    }
+
+    /// Drops the least recently shown art over the limit, it
+    /// can be asked for again, from the image cache
+    fn evict(&mut self) {
+        while self.images.len() > MAX_IMAGES {
+            let on_screen = [&self.wanted, &self.shown, &self.previous];
+            let oldest = self.images.iter().position(|(url, _)| {
+                !on_screen
+                    .iter()
+                    .any(|showing| showing.as_ref() == Some(url))
+            });
+            match oldest {
+                Some(index) => {
+                    let (url, _) = self.images.remove(index);
+                    self.requested.remove(&url);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Starts fading the wanted art in over whatever is showing
+    fn crossfade(&mut self) {
+        if self.shown == self.wanted {
+            return;
+        }
+        // moved to the back so it is the last to be dropped
+        if let Some(url) = self.wanted.as_ref() {
+            if let Some(index) = self.images.iter().position(|(loaded, _)| loaded == url) {
+                let image = self.images.remove(index);
+                self.images.push(image);
+            }
+        }
+        self.previous = self.shown.take();
+        self.shown = self.wanted.clone();
+        self.fade.jump_to(0.0);
+        self.fade
+            .animate_to(1.0, self.timing.fade, self.timing.easing);
+    }
+
+    /// Is the art fading
+    pub fn is_animating(&self) -> bool {
+        self.fade.is_active()
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        self.fade.tick(seconds);
+        if !self.fade.is_active() {
+            self.previous = None;
+        }
+    }
+
+    /// Draws the art over the whole window, darkened
+    /// towards the bottom where the shelves are
+    pub fn draw<T: RenderTarget>(&self, canvas: &mut Canvas<T>, textures: &mut TextureCache<T>) {
+        let viewport = canvas.viewport();
+        let mut drawn = false;
+        if let Some(url) = self.previous.as_ref().filter(|_| self.fade.is_active()) {
+            // with nothing fading in over it, it fades out to black
+            let alpha = match self.shown {
+                Some(_) => 255,
+                None => ((1.0 - self.fade.value()) * 255.0).round() as u8,
+            };
+            drawn |= self.draw_image(canvas, textures, url, alpha, viewport);
+        }
+        if let Some(url) = self.shown.as_ref() {
+            let alpha = (self.fade.value() * 255.0).round() as u8;
+            drawn |= self.draw_image(canvas, textures, url, alpha, viewport);
+        }
+        if drawn {
+            draw_shade(canvas, viewport);
+        }
+    }
+
+    fn draw_image<T: RenderTarget>(
+        &self,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        url: &str,
+        alpha: u8,
+        rect: Rect,
+    ) -> bool {
+        let bytes = match self.bytes(url) {
+            Some(bytes) => bytes,
+            None => return false,
+        };
+        let texture = match textures.image(url, bytes) {
+            Ok(texture) => texture,
+            Err(_) => return false,
+        };
+        let TextureQuery { width, height, .. } = texture.query();
+
+        // the texture is shared, e.g. with the details page,
+        // so it is put back the way it was
+        let blend_mode = texture.blend_mode();
+        texture.set_blend_mode(BlendMode::Blend);
+        texture.set_alpha_mod(alpha);
+        report(canvas.copy(texture, crop_to_fit(width, height, rect), Some(rect)));
+        texture.set_alpha_mod(255);
+        texture.set_blend_mode(blend_mode);
+        true
+    }
+}
+
+/// Darkens the window, more towards the bottom
+fn draw_shade<T: RenderTarget>(canvas: &mut Canvas<T>, rect: Rect) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    let height = rect.height() as i32;
+    for band in 0..SHADE_BANDS {
+        let top = height * band / SHADE_BANDS;
+        let bottom = height * (band + 1) / SHADE_BANDS;
+        let t = band as f32 / (SHADE_BANDS - 1) as f32;
+        let alpha = SHADE_TOP + (SHADE_BOTTOM - SHADE_TOP) * t;
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, alpha as u8));
+        report(canvas.fill_rect(Rect::new(
+            rect.x(),
+            rect.y() + top,
+            rect.width(),
+            (bottom - top).max(1) as u32,
+        )));
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    canvas.set_draw_color(Color::BLACK);
+}
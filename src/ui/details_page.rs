@@ -0,0 +1,175 @@
+use crate::event::DetailsImageLoadEvent;
+use crate::model::home::{ContentSetItem, ItemKind};
+use crate::ui::render::report;
+use crate::ui::texture_cache::TextureCache;
+use bytes::Bytes;
+use log::warn;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, TextureQuery};
+use sdl2::ttf::Font;
+
+const BACKGROUND_SIZE: &str = "1.78";
+const TITLE_TREATMENT_SIZE: &str = "1.78";
+
+/// Shows the full metadata for a single program,
+/// opened from a tile on the home page
+pub struct DetailsPage {
+    item: ContentSetItem,
+    padding: u32,
+    background_url: Option<String>,
+    background: Option<Bytes>,
+    title_treatment_url: Option<String>,
+    title_treatment: Option<Bytes>,
+}
+impl DetailsPage {
+    /// Loads the details page, the images are not
+    /// loaded, see [`DetailsPage::image_urls`]
+    pub fn load(item: ContentSetItem, padding: u32) -> DetailsPage {
+        DetailsPage {
+            background_url: item.background_image_url(BACKGROUND_SIZE).cloned(),
+            title_treatment_url: item.title_treatment_url(TITLE_TREATMENT_SIZE).cloned(),
+            item,
+            padding,
+            background: None,
+            title_treatment: None,
+        }
+    }
+
+    /// Keeps the padding in proportion to the window, the
+    /// rest of the page is laid out from the viewport
+    pub fn on_resize(&mut self, padding: u32) {
+        self.padding = padding;
+    }
+
+    /// The images this page needs loaded
+    pub fn image_urls(&self) -> Vec<String> {
+        self.background_url
+            .iter()
+            .chain(self.title_treatment_url.iter())
+            .cloned()
+            .collect()
+    }
+
+    pub fn on_image_load(&mut self, event: DetailsImageLoadEvent) {
+        if Some(&event.img_url) == self.background_url.as_ref() {
+            self.background = Some(event.bytes);
+        } else if Some(&event.img_url) == self.title_treatment_url.as_ref() {
+            self.title_treatment = Some(event.bytes);
+        } else {
+            warn!("Image is not for this details page {:?}", event.img_url);
+        }
+    }
+
+    pub fn draw<T: RenderTarget>(
+        &mut self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+    ) {
+        let viewport = canvas.viewport();
+
+        // background art fills the screen, darkened so the text is readable
+        // art that fails to decode is left out
+        if let (Some(url), Some(bytes)) = (&self.background_url, &self.background) {
+            if let Ok(texture) = textures.image(url, bytes) {
+                report(canvas.copy(texture, None, Some(viewport)));
+            }
+        }
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+        report(canvas.fill_rect(viewport));
+        canvas.set_blend_mode(BlendMode::None);
+        canvas.set_draw_color(Color::BLACK);
+
+        // the title treatment if we have one, otherwise the plain title
+        let x = self.padding as i32;
+        let mut y = self.padding as i32;
+        let title_treatment = match (&self.title_treatment_url, &self.title_treatment) {
+            (Some(url), Some(bytes)) => textures.image(url, bytes).ok(),
+            _ => None,
+        };
+        match title_treatment {
+            Some(texture) => {
+                let TextureQuery { width, height, .. } = texture.query();
+                let treatment_width = viewport.width() * 2 / 5;
+                let treatment_height = treatment_width * height / width.max(1);
+                let rect = Rect::new(x, y, treatment_width, treatment_height);
+                report(canvas.copy(texture, None, Some(rect)));
+                y = rect.bottom() + self.padding as i32;
+            }
+            None => {
+                if let Some(title) = self.item.title() {
+                    y = draw_text(font, canvas, textures, title, x, y) + self.padding as i32;
+                }
+            }
+        }
+
+        for line in self.metadata_lines() {
+            y = draw_text(font, canvas, textures, &line, x, y) + self.padding as i32 / 2;
+        }
+    }
+
+    /// e.g. "Disney+ Original", "2020 • TV-14 • Series"
+    fn metadata_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.item.is_original() {
+            lines.push("Disney+ Original".to_string());
+        }
+
+        let kind = match self.item.kind() {
+            ItemKind::Series => Some("Series"),
+            ItemKind::Movie => Some("Movie"),
+            ItemKind::Collection => Some("Collection"),
+            ItemKind::Other => None,
+        };
+        let summary: Vec<String> = self
+            .item
+            .release_year()
+            .map(|year| year.to_string())
+            .into_iter()
+            .chain(self.item.rating().cloned())
+            .chain(kind.map(String::from))
+            .collect();
+        if !summary.is_empty() {
+            lines.push(summary.join(" • "));
+        }
+
+        let mut availability = Vec::new();
+        if let Some(current) = self.item.current_availability() {
+            if let Some(region) = &current.region {
+                availability.push(format!("Available in {}", region));
+            }
+            if current.kids_mode == Some(true) {
+                availability.push("Kids".to_string());
+            }
+        }
+        if self.item.media_rights().is_some_and(|r| r.download_blocked) {
+            availability.push("Download unavailable".to_string());
+        }
+        if !availability.is_empty() {
+            lines.push(availability.join(" • "));
+        }
+        lines
+    }
+}
+
+/// Draws a line of text, returning the bottom of the text,
+/// or `y` if it could not be drawn
+fn draw_text<T: RenderTarget>(
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    textures: &mut TextureCache<T>,
+    text: &str,
+    x: i32,
+    y: i32,
+) -> i32 {
+    let texture = match textures.text(font, text) {
+        Ok(texture) => texture,
+        Err(_) => return y,
+    };
+    let TextureQuery { width, height, .. } = texture.query();
+    let rect = Rect::new(x, y, width, height);
+    report(canvas.copy(texture, None, Some(rect)));
+    rect.bottom()
+}
@@ -0,0 +1,344 @@
+//! Renders the ui offscreen, without a window or a display
+//!
+//! The ui draws onto any [`RenderTarget`], the app draws onto its window
+//! while screenshots are drawn by the software renderer onto a [`Surface`].
+//! A [`Screenshot`] loads fixture content, is driven by key presses like
+//! the event loop, and renders a [`Frame`] to compare against golden PNGs
+//! under `tests/golden`
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Error, Result};
+use bytes::Bytes;
+use futures::StreamExt;
+use log::warn;
+use sdl2::image::{InitFlag, LoadSurface, SaveSurface};
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::surface::Surface;
+
+use crate::config::Config;
+use crate::event::{ImageLoadEvent, TileRef};
+use crate::input::{Action, Bindings, Direction, Pointer};
+use crate::model::home::ContentSet;
+use crate::service::disney::DisneyService;
+use crate::service::source::ContentSource;
+use crate::ui::animation::Timing;
+use crate::ui::details_page::DetailsPage;
+use crate::ui::home_page::HomePage;
+use crate::ui::layout::Layout;
+use crate::ui::texture_cache::TextureCache;
+
+/// SDL_ttf and SDL_image are not thread safe, and tests run
+/// in parallel, so only one frame is rendered at a time
+static RENDER_LOCK: Mutex<()> = Mutex::new(());
+
+/// The golden images, relative to the crate root
+const GOLDEN_DIR: &str = "tests/golden";
+
+/// Frames that do not match their golden are written here
+const ACTUAL_DIR: &str = "target/screenshots";
+
+/// Long enough for any animation or fling to come to rest
+const SETTLE_SECONDS: f32 = 10.0;
+
+/// Set to rewrite the golden images rather than compare against them
+const UPDATE_GOLDENS_ENV: &str = "UPDATE_GOLDENS";
+
+/// Loads the home screen content sets from a fixture directory,
+/// laid out like the api, see [`ContentSource::Directory`]. Sets
+/// that fail to load, e.g. a set ref without a fixture, are left
+/// out as they are by the app
+pub async fn load_fixture(dir: &Path) -> Result<Vec<ContentSet>> {
+    let disney = DisneyService::new(1, ContentSource::Directory(dir.to_path_buf()));
+    let content_sets = disney.stream_home_content_sets().await?;
+    Ok(content_sets
+        .filter_map(|result| async move {
+            result
+                .map_err(|e| warn!("Failure loading content set: {:#}", e))
+                .ok()
+        })
+        .collect()
+        .await)
+}
+
+/// The ui at a point in time, as seen after a series of key presses
+pub struct Screenshot {
+    config: Config,
+    layout: Layout,
+    home: HomePage,
+    details: Option<DetailsPage>,
+}
+impl Screenshot {
+    pub fn new(config: Config, content_sets: Vec<ContentSet>) -> Screenshot {
+        let layout = Layout::new(&config, config.window_width, config.window_height);
+        let timing = Timing::new(&config);
+        let mut screenshot = Screenshot {
+            home: HomePage::load(content_sets, layout, &config.image_size, timing),
+            config,
+            layout,
+            details: None,
+        };
+        screenshot.settle();
+        screenshot
+    }
+
+    /// Resizes the offscreen window, as if the window was resized
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.config.window_width = width;
+        self.config.window_height = height;
+        self.layout = Layout::new(&self.config, width, height);
+        self.home.on_resize(self.layout);
+        if let Some(details) = self.details.as_mut() {
+            details.on_resize(self.layout.shelf_padding);
+        }
+    }
+
+    /// Handles a key the same way the event loop does,
+    /// with the default bindings
+    pub fn press(&mut self, key: Keycode) {
+        if let Some(action) = Bindings::default().key_action(key) {
+            self.act(action);
+        }
+    }
+
+    /// Handles an action the same way the event loop does, and
+    /// waits for the animations it started to finish
+    pub fn act(&mut self, action: Action) {
+        self.begin(action);
+        self.settle();
+    }
+
+    /// Handles an action the same way the event loop does, leaving its
+    /// animations at the start, see [`Screenshot::wait`]. Pages are never
+    /// requested since the fixture content is fixed
+    pub fn begin(&mut self, action: Action) {
+        match (action, self.details.is_some()) {
+            (Action::Back | Action::Quit, true) => self.details = None,
+            (Action::Back | Action::Quit, false) => {
+                self.home.on_back();
+            }
+            (Action::Select, false) => {
+                self.details = self
+                    .home
+                    .selected_item()
+                    .map(|item| DetailsPage::load(item.clone(), self.layout.shelf_padding));
+            }
+            (Action::Navigate(Direction::Left), false) => self.home.on_key_left(),
+            (Action::Navigate(Direction::Right), false) => {
+                self.home.on_key_right();
+            }
+            (Action::Navigate(Direction::Down), false) => self.home.on_key_down(),
+            (Action::Navigate(Direction::Up), false) => self.home.on_key_up(),
+            (Action::PageLeft, false) => self.home.on_page_left(),
+            (Action::PageRight, false) => {
+                self.home.on_page_right();
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles a mouse or touch gesture the same way the event loop does
+    pub fn point(&mut self, gesture: Pointer) {
+        if self.details.is_some() {
+            return;
+        }
+        match gesture {
+            Pointer::Hover { x, y } => {
+                self.home.on_hover(x, y);
+            }
+            Pointer::Tap { x, y } => {
+                self.home.on_hover(x, y);
+                if self.home.tile_at(x, y).is_some() || self.home.hero_at(x, y) {
+                    self.act(Action::Select);
+                }
+            }
+            Pointer::Wheel { y, dx, dy, .. } => self.home.on_wheel(y, dx, dy),
+            Pointer::Drag { y, dx, dy, .. } => {
+                self.home.on_drag(y, dx, dy);
+            }
+            Pointer::Fling { y, velocity_x, .. } => self.home.on_fling(y, velocity_x),
+        }
+        self.settle();
+    }
+
+    /// Lets anything that is moving come to rest
+    fn settle(&mut self) {
+        self.wait(SETTLE_SECONDS);
+    }
+
+    /// Lets time pass, in steps of a 60hz frame, for anything still moving
+    pub fn wait(&mut self, seconds: f32) {
+        let frame = 1.0 / 60.0;
+        let mut waited = 0.0;
+        while waited < seconds && self.home.is_animating() {
+            self.home.tick(frame);
+            waited += frame;
+        }
+    }
+
+    /// Gives a tile an image, as if it had loaded, e.g. to see how a
+    /// corrupt image is drawn. False if the tile has no artwork
+    pub fn load_tile_image(&mut self, row: usize, tile: usize, bytes: Bytes) -> bool {
+        let img_url = match self.home.tile_image_url(row, tile) {
+            Some(img_url) => img_url.clone(),
+            None => return false,
+        };
+        self.home.on_image_load(ImageLoadEvent {
+            img_url,
+            bytes,
+            tiles: vec![TileRef { shelf: row, tile }],
+        });
+        true
+    }
+
+    pub fn press_all(&mut self, keys: &[Keycode]) {
+        keys.iter().for_each(|key| self.press(*key));
+    }
+
+    /// Draws a frame the same way the event loop does,
+    /// onto an offscreen surface the size of the window
+    pub fn render(&mut self) -> Result<Frame> {
+        let _lock = RENDER_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let _image_context =
+            sdl2::image::init(InitFlag::PNG | InitFlag::JPG).map_err(Error::msg)?;
+        let ttf_context = sdl2::ttf::init()?;
+        let font = ttf_context
+            .load_font(&self.config.font_path, self.layout.font_size)
+            .map_err(Error::msg)?;
+
+        let (width, height) = (self.config.window_width, self.config.window_height);
+        let surface = Surface::new(width, height, PixelFormatEnum::RGBA32).map_err(Error::msg)?;
+        let mut canvas = surface.into_canvas().map_err(Error::msg)?;
+        let texture_creator = canvas.texture_creator();
+        let mut textures = TextureCache::new(&texture_creator, self.config.texture_max_bytes);
+
+        textures.begin_frame();
+        canvas.set_draw_color(Color::BLACK);
+        canvas.clear();
+        if let Some(details) = self.details.as_mut() {
+            details.draw(&font, &mut canvas, &mut textures);
+        } else {
+            self.home.draw(&font, &mut canvas, &mut textures);
+        }
+        canvas.present();
+        textures.end_frame();
+
+        let pixels = canvas
+            .read_pixels(None, PixelFormatEnum::RGBA32)
+            .map_err(Error::msg)?;
+        Ok(Frame {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// A rendered frame, as tightly packed RGBA pixels
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+impl Frame {
+    pub fn load_png(path: &Path) -> Result<Frame> {
+        let surface = Surface::from_file(path)
+            .and_then(|s| s.convert_format(PixelFormatEnum::RGBA32))
+            .map_err(|e| anyhow!("Unable to load {:?}: {}", path, e))?;
+        let (width, height) = (surface.width(), surface.height());
+
+        // rows may be padded, so copy out just the pixels
+        let row_bytes = width as usize * 4;
+        let pitch = surface.pitch() as usize;
+        let pixels = surface.with_lock(|data| {
+            data.chunks(pitch)
+                .take(height as usize)
+                .flat_map(|row| row[..row_bytes].iter().copied())
+                .collect()
+        });
+        Ok(Frame {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn save_png(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut pixels = self.pixels.clone();
+        let surface = Surface::from_data(
+            &mut pixels,
+            self.width,
+            self.height,
+            self.width * 4,
+            PixelFormatEnum::RGBA32,
+        )
+        .map_err(Error::msg)?;
+        surface
+            .save(path)
+            .map_err(|e| anyhow!("Unable to save {:?}: {}", path, e))
+    }
+
+    /// The number of pixels with a channel that differs by more than
+    /// the tolerance, to allow for small differences in font rasterizing
+    pub fn diff(&self, other: &Frame, tolerance: u8) -> Result<usize> {
+        if (self.width, self.height) != (other.width, other.height) {
+            return Err(anyhow!(
+                "Frame is {}x{}, expected {}x{}",
+                self.width,
+                self.height,
+                other.width,
+                other.height
+            ));
+        }
+        Ok(self
+            .pixels
+            .chunks(4)
+            .zip(other.pixels.chunks(4))
+            .filter(|(a, b)| {
+                a.iter()
+                    .zip(b.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > tolerance)
+            })
+            .count())
+    }
+
+    /// Compares the frame against the named golden image
+    ///
+    /// Every golden is written from its frame when `UPDATE_GOLDENS` is
+    /// set, otherwise a missing golden is an error. On a mismatch the
+    /// frame is written under `target/screenshots` for inspection
+    pub fn assert_golden(&self, name: &str) -> Result<()> {
+        let golden_path = PathBuf::from(GOLDEN_DIR).join(format!("{}.png", name));
+        if std::env::var_os(UPDATE_GOLDENS_ENV).is_some() {
+            return self.save_png(&golden_path);
+        }
+        if !golden_path.exists() {
+            let actual_path = PathBuf::from(ACTUAL_DIR).join(format!("{}.png", name));
+            self.save_png(&actual_path)?;
+            return Err(anyhow!(
+                "No golden at {:?}, see {:?} and run with {}=1 to write it",
+                golden_path,
+                actual_path,
+                UPDATE_GOLDENS_ENV
+            ));
+        }
+
+        let golden = Frame::load_png(&golden_path)?;
+        let mismatched = self.diff(&golden, 2)?;
+        if mismatched > 0 {
+            let actual_path = PathBuf::from(ACTUAL_DIR).join(format!("{}.png", name));
+            self.save_png(&actual_path)?;
+            return Err(anyhow!(
+                "{} pixels differ from {:?}, see {:?}",
+                mismatched,
+                golden_path,
+                actual_path
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,383 @@
+use crate::event::HeroImageLoadEvent;
+use crate::model::home::{ContentSet, ContentSetItem};
+use crate::ui::animation::{Timing, Tween};
+use crate::ui::layout::Layout;
+use crate::ui::render::report;
+use crate::ui::scene::Transform;
+use crate::ui::texture_cache::TextureCache;
+use crate::ui::tile::crop_to_fit;
+use bytes::Bytes;
+use log::warn;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget, TextureQuery};
+use sdl2::ttf::Font;
+
+/// Width over height of the carousel, the widest hero art
+const HERO_ASPECT_RATIO: f32 = 3.91;
+
+/// The hero art and title treatment layer variants, most preferred first
+const HERO_IMAGE_SIZES: &[&str] = &["3.91", "3.00"];
+
+/// The carousel takes no more than this share of the window height
+const HERO_MAX_HEIGHT: f32 = 0.4;
+
+/// The most programs the carousel cycles through
+const HERO_MAX_SLIDES: usize = 6;
+
+/// A program in the carousel, its art with its title over the top
+struct HeroSlide {
+    item: ContentSetItem,
+    background_url: Option<String>,
+    background: Option<Bytes>,
+    title_url: Option<String>,
+    title: Option<Bytes>,
+}
+impl HeroSlide {
+    fn load(item: ContentSetItem) -> HeroSlide {
+        HeroSlide {
+            background_url: item.hero_image_url(HERO_IMAGE_SIZES).cloned(),
+            title_url: item.title_treatment_layer_url(HERO_IMAGE_SIZES).cloned(),
+            item,
+            background: None,
+            title: None,
+        }
+    }
+}
+
+/// A full width row above the shelves, featuring the first
+/// programs on the home screen one at a time
+///
+/// The carousel moves on to the next program by itself every
+/// [`Timing::hero_interval`], the count starts again whenever the
+/// user does anything. When focused, left and right move through
+/// the programs
+pub struct HeroCarousel {
+    slides: Vec<HeroSlide>,
+    current: usize,
+    // the slide moving out as the current one slides in, and
+    // which way they are going, 1 is to the left
+    previous: Option<(usize, i32)>,
+    slide: Tween,
+    focused: bool,
+    timing: Timing,
+    // seconds until the carousel moves on by itself
+    countdown: f32,
+    // on the home page, before the home page scrolls
+    rect: Rect,
+}
+impl HeroCarousel {
+    /// Features the first programs of a content set, None
+    /// if it does not have any
+    pub fn load(content_set: &ContentSet, layout: &Layout, timing: Timing) -> Option<HeroCarousel> {
+        let slides: Vec<HeroSlide> = content_set
+            .items_iter()
+            .take(HERO_MAX_SLIDES)
+            .cloned()
+            .map(HeroSlide::load)
+            .collect();
+        if slides.is_empty() {
+            return None;
+        }
+        Some(HeroCarousel {
+            slides,
+            current: 0,
+            previous: None,
+            slide: Tween::new(1.0),
+            focused: false,
+            timing,
+            countdown: timing.hero_interval,
+            rect: HeroCarousel::layout_rect(layout),
+        })
+    }
+
+    /// As wide as the shelves, and as tall as the hero art allows
+    fn layout_rect(layout: &Layout) -> Rect {
+        let width = layout.width.saturating_sub(layout.tile_padding * 2).max(1);
+        let height = (width as f32 / HERO_ASPECT_RATIO)
+            .min(layout.height as f32 * HERO_MAX_HEIGHT)
+            .max(1.0) as u32;
+        Rect::new(
+            layout.tile_padding as i32,
+            layout.shelf_padding as i32,
+            width,
+            height,
+        )
+    }
+
+    /// Lays the carousel out for the new window size, finishing any slide
+    pub fn on_resize(&mut self, layout: &Layout) {
+        self.rect = HeroCarousel::layout_rect(layout);
+        self.slide.jump_to(1.0);
+        self.previous = None;
+    }
+
+    /// The carousel on the home page, before the home page scrolls
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    /// The images the carousel needs loaded
+    pub fn image_urls(&self) -> Vec<String> {
+        self.slides
+            .iter()
+            .flat_map(|slide| slide.background_url.iter().chain(slide.title_url.iter()))
+            .cloned()
+            .collect()
+    }
+
+    /// An image has loaded, a title treatment that failed is
+    /// forgotten so the slide shows the plain title instead
+    pub fn on_image_load(&mut self, event: HeroImageLoadEvent) {
+        let mut found = false;
+        // programs can share art, so every slide is checked
+        for slide in self.slides.iter_mut() {
+            if slide.background_url.as_ref() == Some(&event.img_url) {
+                slide.background = event.bytes.clone();
+                found = true;
+            }
+            if slide.title_url.as_ref() == Some(&event.img_url) {
+                slide.title = event.bytes.clone();
+                if slide.title.is_none() {
+                    slide.title_url = None;
+                }
+                found = true;
+            }
+        }
+        if !found {
+            warn!("Image is not for the hero carousel {:?}", event.img_url);
+        }
+    }
+
+    /// The program being featured
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn selected_item(&self) -> &ContentSetItem {
+        &self.slides[self.current].item
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn focus(&mut self) {
+        self.focused = true;
+    }
+
+    pub fn unfocus(&mut self) {
+        self.focused = false;
+    }
+
+    /// Slides in the next program, wrapping around to the first
+    pub fn on_key_right(&mut self) {
+        self.hold();
+        self.advance(1);
+    }
+
+    /// Slides in the previous program, wrapping around to the last
+    pub fn on_key_left(&mut self) {
+        self.hold();
+        self.advance(-1);
+    }
+
+    /// The user did something, the carousel waits
+    /// a full interval before moving on by itself
+    pub fn hold(&mut self) {
+        self.countdown = self.timing.hero_interval;
+    }
+
+    fn advance(&mut self, direction: i32) {
+        let count = self.slides.len() as i32;
+        if count < 2 {
+            return;
+        }
+        self.previous = Some((self.current, direction));
+        self.current = (self.current as i32 + direction).rem_euclid(count) as usize;
+        self.slide.jump_to(0.0);
+        self.slide
+            .animate_to(1.0, self.timing.scroll, self.timing.easing);
+    }
+
+    /// Is a slide moving in
+    pub fn is_animating(&self) -> bool {
+        self.slide.is_active()
+    }
+
+    /// Moves the slide along, and the carousel on to the
+    /// next program when its interval is up
+    pub fn tick(&mut self, seconds: f32) {
+        self.slide.tick(seconds);
+        if self.time_to_advance().is_some() {
+            self.countdown -= seconds;
+            if self.countdown <= 0.0 {
+                self.countdown = self.timing.hero_interval;
+                self.advance(1);
+            }
+        }
+    }
+
+    /// Seconds until the carousel moves on by itself,
+    /// None if it never does
+    pub fn time_to_advance(&self) -> Option<f32> {
+        if self.timing.hero_interval > 0.0 && self.slides.len() > 1 {
+            Some(self.countdown.max(0.0))
+        } else {
+            None
+        }
+    }
+
+    /// Is a point on screen on the carousel
+    pub fn contains(&self, page: Transform, x: i32, y: i32) -> bool {
+        let (x, y) = page.unapply(x, y);
+        self.rect.contains_point((x, y))
+    }
+
+    /// Draws the carousel, moved onto the screen by the home page's transform
+    pub fn draw<T: RenderTarget>(
+        &self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        page: Transform,
+    ) {
+        let rect = page.apply(self.rect);
+        let viewport = canvas.viewport();
+        if rect.bottom() < viewport.top() || rect.top() > viewport.bottom() {
+            return;
+        }
+
+        // outline the carousel like a selected tile
+        if self.focused {
+            canvas.set_draw_color(Color::WHITE);
+            report(canvas.fill_rect(Rect::new(
+                rect.x() - 5,
+                rect.y() - 5,
+                rect.width() + 10,
+                rect.height() + 10,
+            )));
+            canvas.set_draw_color(Color::BLACK);
+        }
+
+        // the slides move across together, the current one sliding in
+        // from the side as the previous one slides out the other
+        canvas.set_clip_rect(rect);
+        let width = rect.width() as f32;
+        let progress = self.slide.value();
+        match self.previous {
+            Some((previous, direction)) if self.slide.is_active() => {
+                let out = -(direction as f32 * progress * width).round() as i32;
+                let into = (direction as f32 * (1.0 - progress) * width).round() as i32;
+                self.draw_slide(
+                    font,
+                    canvas,
+                    textures,
+                    previous,
+                    Transform::new(out, 0).apply(rect),
+                );
+                self.draw_slide(
+                    font,
+                    canvas,
+                    textures,
+                    self.current,
+                    Transform::new(into, 0).apply(rect),
+                );
+            }
+            _ => self.draw_slide(font, canvas, textures, self.current, rect),
+        }
+        canvas.set_clip_rect(None);
+
+        self.draw_dots(canvas, rect);
+    }
+
+    /// The art with the title treatment layer over it, falling
+    /// back to a dark background and the plain title
+    fn draw_slide<T: RenderTarget>(
+        &self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        index: usize,
+        rect: Rect,
+    ) {
+        // art that is loading, or failed to decode, falls back the same
+        let slide = &self.slides[index];
+        let background = match (&slide.background_url, &slide.background) {
+            (Some(url), Some(bytes)) => textures.image(url, bytes).ok(),
+            _ => None,
+        };
+        match background {
+            Some(texture) => {
+                let TextureQuery { width, height, .. } = texture.query();
+                report(canvas.copy(texture, crop_to_fit(width, height, rect), Some(rect)));
+            }
+            None => {
+                canvas.set_draw_color(Color::RGB(40, 40, 48));
+                report(canvas.fill_rect(rect));
+                canvas.set_draw_color(Color::BLACK);
+            }
+        }
+
+        let title_loading = slide.title_url.is_some() && slide.title.is_none();
+        let title_layer = match (&slide.title_url, &slide.title) {
+            (Some(url), Some(bytes)) => textures.image(url, bytes).ok(),
+            _ => None,
+        };
+        if let Some(texture) = title_layer {
+            // the layer is transparent around the title, and the same shape as the art
+            let TextureQuery { width, height, .. } = texture.query();
+            report(canvas.copy(texture, crop_to_fit(width, height, rect), Some(rect)));
+        } else if !title_loading {
+            if let Some(title) = slide.item.title() {
+                draw_title(font, canvas, textures, title, rect);
+            }
+        }
+    }
+
+    /// A dot for each program, the current one filled in
+    fn draw_dots<T: RenderTarget>(&self, canvas: &mut Canvas<T>, rect: Rect) {
+        if self.slides.len() < 2 {
+            return;
+        }
+        let size = (rect.height() / 40).max(4);
+        let gap = size as i32;
+        let margin = rect.height() as i32 / 16;
+        let mut x = rect.right() - margin - self.slides.len() as i32 * (size as i32 + gap);
+        let y = rect.bottom() - margin - size as i32;
+        for index in 0..self.slides.len() {
+            let dot = Rect::new(x, y, size, size);
+            canvas.set_draw_color(Color::WHITE);
+            if index == self.current {
+                report(canvas.fill_rect(dot));
+            } else {
+                report(canvas.draw_rect(dot));
+            }
+            x += size as i32 + gap;
+        }
+        canvas.set_draw_color(Color::BLACK);
+    }
+}
+
+/// The plain title, bottom left of the slide
+fn draw_title<T: RenderTarget>(
+    font: &Font,
+    canvas: &mut Canvas<T>,
+    textures: &mut TextureCache<T>,
+    title: &str,
+    rect: Rect,
+) {
+    let texture = match textures.text(font, title) {
+        Ok(texture) => texture,
+        Err(_) => return,
+    };
+    let TextureQuery { width, height, .. } = texture.query();
+    let margin = rect.height() as i32 / 8;
+    let title_rect = Rect::new(
+        rect.x() + margin,
+        rect.bottom() - margin - height as i32,
+        width,
+        height,
+    );
+    report(canvas.copy(texture, None, Some(title_rect)));
+}
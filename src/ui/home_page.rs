@@ -1,37 +1,79 @@
-use crate::event::ImageLoadEvent;
-use crate::model::home::ContentSet;
-use crate::ui::shelf::Shelf;
+use crate::event::{BackgroundImageLoadEvent, HeroImageLoadEvent, ImageLoadEvent, TileRef};
+use crate::model::home::{ContentSet, ContentSetItem};
+use crate::service::image_queue::Viewport;
+use crate::ui::animation::{Timing, Tween};
+use crate::ui::backdrop::Backdrop;
+use crate::ui::hero::HeroCarousel;
+use crate::ui::layout::Layout;
+use crate::ui::scene::Transform;
+use crate::ui::shelf::{PageRequest, Shelf};
+use crate::ui::texture_cache::TextureCache;
 use log::warn;
-use sdl2::render::Canvas;
+use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget};
 use sdl2::ttf::Font;
-use sdl2::video::Window;
+use std::collections::HashMap;
+
+/// Momentum added to a shelf for each notch of a horizontal
+/// wheel or trackpad scroll, in pixels a second
+const WHEEL_NOTCH_VELOCITY: f32 = 1500.0;
+
+/// What is selected on the home page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Focus {
+    /// The hero carousel, on one of its programs
+    Hero(usize),
+    /// A tile on a shelf
+    Tile { row: usize, tile: usize },
+}
 
 /// The main screen for the application
 /// The root of the application
 pub struct HomePage {
-    shelf_padding: u32,
-    tile_padding: u32,
+    layout: Layout,
+    // the preferred tile image variant, see TileShape::image_sizes
+    image_size: String,
+    timing: Timing,
+    // the focused program's art, behind everything
+    backdrop: Backdrop,
+    // above the shelves, featuring the first content set
+    hero: Option<HeroCarousel>,
     shelves: Vec<Shelf>,
+    // every tile showing each image, so one download
+    // reaches the same program on other shelves
+    tiles_by_url: HashMap<String, Vec<TileRef>>,
     selected_row: usize,
-    shelf_height: u32,
+    // how far the shelves are scrolled up, in pixels,
+    // the shelves themselves stay where they were laid out
+    scroll: Tween,
+    // vertical wheel notches and touch drag pixels not yet
+    // turned into a row, a trackpad scrolls in small steps
+    wheel_rows: f32,
+    drag_y: f32,
 }
 impl HomePage {
     /// Loads a home page component, but does
     /// not do any rendering
     pub fn load(
         content_sets: Vec<ContentSet>,
-        shelf_padding: u32,
-        shelf_height: u32,
-        tile_padding: u32,
+        layout: Layout,
+        image_size: &str,
+        timing: Timing,
     ) -> HomePage {
         // the parent is responsible for positioning the children
         // here, we place each new shelf below the other
         let mut home_page = HomePage {
-            shelf_padding,
-            tile_padding,
+            layout,
+            image_size: image_size.to_string(),
+            timing,
+            backdrop: Backdrop::new(timing),
+            hero: None,
             shelves: Vec::new(),
+            tiles_by_url: HashMap::new(),
             selected_row: 0,
-            shelf_height,
+            scroll: Tween::new(0.0),
+            wheel_rows: 0.0,
+            drag_y: 0.0,
         };
         home_page.load_shelves(content_sets);
         home_page
@@ -39,89 +81,557 @@ impl HomePage {
 
     /// Load the shelves onto the home screen
     fn load_shelves(&mut self, content_sets: Vec<ContentSet>) {
-        // Maintain proper positioning on create
-        // Each shelf should initially be before the one below it
-        let mut y_pos = self.shelf_padding as i32;
         for cs in content_sets {
-            let next_shelf = Shelf::load(cs, self.shelf_height, self.tile_padding, y_pos);
-            y_pos = next_shelf.y() + next_shelf.height() as i32 + self.shelf_padding as i32;
-            self.shelves.push(next_shelf);
+            self.add_shelf(cs);
+        }
+    }
+
+    /// Adds a shelf below the last shelf, content sets
+    /// can be added as they finish loading
+    ///
+    /// The first content set also fills the hero carousel,
+    /// returns the images the carousel needs loaded
+    pub fn add_shelf(&mut self, content_set: ContentSet) -> Vec<String> {
+        let mut hero_image_urls = Vec::new();
+        if self.hero.is_none() && self.shelves.is_empty() {
+            self.hero = HeroCarousel::load(&content_set, &self.layout, self.timing);
+            hero_image_urls = self
+                .hero
+                .as_ref()
+                .map(|hero| hero.image_urls())
+                .unwrap_or_default();
         }
 
+        // Maintain proper positioning on create
+        // Each shelf should initially be before the one below it
+        let y_pos = self.shelf_y(self.shelves.len());
+        let mut shelf = Shelf::load(
+            content_set,
+            &self.layout,
+            y_pos,
+            &self.image_size,
+            self.timing,
+        );
+
         // select the first row so that when the
         // screen appears we should be ready to go
-        if let Some(shelf) = self.shelves.get_mut(0) {
-            shelf.select()
+        if self.shelves.is_empty() {
+            shelf.select();
+        }
+        self.shelves.push(shelf);
+        self.index_tiles(self.shelves.len() - 1, 0);
+        hero_image_urls
+    }
+
+    /// Adds the tiles of a shelf from `first` on to the image index
+    fn index_tiles(&mut self, shelf_index: usize, first: usize) {
+        for (tile, url) in self.shelves[shelf_index].image_urls(first) {
+            self.tiles_by_url
+                .entry(url.clone())
+                .or_default()
+                .push(TileRef {
+                    shelf: shelf_index,
+                    tile,
+                });
+        }
+    }
+
+    /// An image for the hero carousel has loaded
+    pub fn on_hero_image_load(&mut self, event: HeroImageLoadEvent) {
+        if let Some(hero) = self.hero.as_mut() {
+            hero.on_image_load(event);
+        }
+    }
+
+    fn hero_focused(&self) -> bool {
+        self.hero.as_ref().is_some_and(|hero| hero.is_focused())
+    }
+
+    /// Moves the focus from the hero down to the first shelf
+    fn unfocus_hero(&mut self) {
+        if let Some(hero) = self.hero.as_mut() {
+            hero.unfocus();
+        }
+        if let Some(shelf) = self.shelves.get_mut(self.selected_row) {
+            shelf.select();
+        }
+    }
+
+    /// The user did something, the hero waits before moving on
+    fn hold_hero(&mut self) {
+        if let Some(hero) = self.hero.as_mut() {
+            hero.hold();
+        }
+    }
+
+    /// Background art has loaded
+    pub fn on_background_image_load(&mut self, event: BackgroundImageLoadEvent) {
+        self.backdrop.on_image_load(event);
+    }
+
+    /// Points the background at the focused program, and returns the
+    /// art it needs loaded, for the focus and the tiles a key press
+    /// away from it. Called after handling input
+    pub fn take_image_requests(&mut self) -> Vec<String> {
+        let mut neighbours = Vec::new();
+        let focus = self.focus();
+        if let Focus::Tile { row, tile } = focus {
+            if let Some(shelf) = self.shelves.get(row) {
+                neighbours.extend(tile.checked_sub(1).and_then(|left| shelf.item(left)));
+                neighbours.extend(shelf.item(tile + 1));
+            }
+            if let Some(above) = row.checked_sub(1).and_then(|up| self.shelves.get(up)) {
+                neighbours.extend(above.selected_item());
+            }
+        }
+        let below = match focus {
+            Focus::Hero(_) => self.shelves.get(self.selected_row),
+            Focus::Tile { row, .. } => self.shelves.get(row + 1),
         };
+        neighbours.extend(below.and_then(|shelf| shelf.selected_item()));
+
+        let wanted = self.selected_item().and_then(Backdrop::image_url).cloned();
+        let neighbours = neighbours
+            .into_iter()
+            .filter_map(Backdrop::image_url)
+            .cloned()
+            .collect();
+        self.backdrop.follow(wanted, neighbours);
+        self.backdrop.take_requests()
+    }
+
+    /// Lays out every shelf for the new window size, keeping
+    /// the selection and scroll position
+    pub fn on_resize(&mut self, layout: Layout) {
+        self.layout = layout;
+        if let Some(hero) = self.hero.as_mut() {
+            hero.on_resize(&layout);
+        }
+
+        self.scroll.jump_to(self.selected_scroll());
+        for index in 0..self.shelves.len() {
+            let y = self.shelf_y(index);
+            self.shelves[index].on_resize(&layout, y);
+        }
+    }
+
+    /// The top of a shelf, they are laid out top to bottom below the hero
+    fn shelf_y(&self, index: usize) -> i32 {
+        let top = self
+            .hero
+            .as_ref()
+            .map(|hero| hero.rect().bottom())
+            .unwrap_or(0);
+        top + self.layout.shelf_padding as i32 + index as i32 * self.shelf_step()
+    }
+
+    /// Where the hero carousel is on screen, if there is one
+    pub fn hero_rect(&self) -> Option<Rect> {
+        let hero = self.hero.as_ref()?;
+        Some(self.transform().apply(hero.rect()))
+    }
+
+    /// Moves the shelves onto the screen, scrolled up
+    fn transform(&self) -> Transform {
+        Transform::new(0, -(self.scroll.value().round() as i32))
+    }
+
+    /// Where a shelf is on screen
+    pub fn shelf_rect(&self, row: usize) -> Option<Rect> {
+        let shelf = self.shelves.get(row)?;
+        Some(self.transform().apply(shelf.rect()))
+    }
+
+    /// The artwork of a tile, None if it has none
+    pub fn tile_image_url(&self, row: usize, tile: usize) -> Option<&String> {
+        self.shelves.get(row)?.tile_image_url(tile)
+    }
+
+    /// Where a tile is on screen
+    pub fn tile_rect(&self, row: usize, tile: usize) -> Option<Rect> {
+        self.shelves.get(row)?.tile_rect(self.transform(), tile)
+    }
+
+    /// Where the selected row is in view, the rows above it scroll
+    /// off the top once we are past the 2nd row
+    fn selected_scroll(&self) -> f32 {
+        (self.selected_row.saturating_sub(1) as i32 * self.shelf_step()) as f32
+    }
+
+    /// Scrolls the selected row into view, over the scroll animation.
+    /// The selection may have moved by pointer since the last key press
+    fn scroll_to_selected(&mut self) {
+        self.scroll.animate_to(
+            self.selected_scroll(),
+            self.timing.scroll,
+            self.timing.easing,
+        );
+    }
+
+    /// The distance from the top of one shelf to the next
+    fn shelf_step(&self) -> i32 {
+        self.layout.shelf_height as i32 + self.layout.shelf_padding as i32
     }
 
     /// Re-render whatever is needed
-    pub fn draw(&mut self, font: &Font, canvas: &mut Canvas<Window>) {
-        self.shelves.iter_mut().for_each(|s| s.draw(font, canvas));
+    pub fn draw<T: RenderTarget>(
+        &mut self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+    ) {
+        self.backdrop.draw(canvas, textures);
+        let transform = self.transform();
+        if let Some(hero) = self.hero.as_ref() {
+            hero.draw(font, canvas, textures, transform);
+        }
+        self.shelves
+            .iter_mut()
+            .for_each(|s| s.draw(font, canvas, textures, transform));
     }
 
-    /// Process an image load event, find the tile and
-    /// send away
+    /// Process an image load event, giving the image to the tiles it
+    /// was loaded for, and any other tiles showing it still waiting
     pub fn on_image_load(&mut self, event: ImageLoadEvent) {
-        let maybe_found_row = self
-            .shelves
-            .iter_mut()
-            .find(|row| *row.title() == event.content_set_title);
-        if let Some(found_row) = maybe_found_row {
-            found_row.on_image_load(event);
-        } else {
-            warn!(
-                "Did not find row for event; content set title = {:?}",
-                event.content_set_title
-            );
+        for tile in &event.tiles {
+            let found = self
+                .shelves
+                .get_mut(tile.shelf)
+                .is_some_and(|shelf| shelf.set_image(tile.tile, &event.img_url, &event.bytes));
+            if !found {
+                warn!("Did not find tile {:?} for image {:?}", tile, event.img_url);
+            }
+        }
+
+        let sharing = self.tiles_by_url.get(&event.img_url).into_iter().flatten();
+        for tile in sharing {
+            if let Some(shelf) = self.shelves.get_mut(tile.shelf) {
+                if !shelf.has_image(tile.tile) {
+                    shelf.set_image(tile.tile, &event.img_url, &event.bytes);
+                }
+            }
         }
     }
 
     pub fn on_key_down(&mut self) {
+        self.hold_hero();
+        if self.hero_focused() {
+            self.unfocus_hero();
+            return;
+        }
+
         // when going down, we have to subtract the height from all shelves, forcing some to go negative
         // unless the current selection is the last row
         let current_selection = self.selected_row;
-        if current_selection < self.shelves.len() - 1 {
-            // only slide if we are past the 2nd row
-            // otherwise, leave the rows alone
-
+        if current_selection + 1 < self.shelves.len() {
             // unselect the current selection
             self.shelves[current_selection].unselect();
-            if current_selection > 0 {
-                for shelf in self.shelves.iter_mut() {
-                    // move the shelves up (scroll down)]
-                    // by shifting the y position for each shelf up
-                    shelf.set_y(shelf.y() - shelf.height() as i32 - self.shelf_padding as i32);
-                }
-            }
             self.selected_row += 1;
             self.shelves[self.selected_row].select();
+            // move the shelves up (scroll down)
+            self.scroll_to_selected();
         }
     }
 
     pub fn on_key_up(&mut self) {
+        self.hold_hero();
         // when scrolling up, we do not scroll past 0
         let current_selection = self.selected_row;
-        if current_selection > 0 {
-            self.shelves[current_selection].unselect();
-            // only shift up if we aren't going to 0
-            if current_selection >= 2 {
-                for shelf in self.shelves.iter_mut() {
-                    shelf.set_y(shelf.y() + shelf.height() as i32 + self.shelf_padding as i32);
+        if current_selection == 0 && !self.hero_focused() {
+            // up from the first row is the hero, if there is one
+            if let Some(hero) = self.hero.as_mut() {
+                hero.focus();
+                if let Some(shelf) = self.shelves.get_mut(current_selection) {
+                    shelf.unselect();
                 }
             }
+        } else if current_selection > 0 {
+            self.shelves[current_selection].unselect();
             self.selected_row -= 1;
             self.shelves[self.selected_row].select();
+            self.scroll_to_selected();
         }
     }
 
-    pub fn on_key_right(&mut self) {
+    /// Back returns the focus to the top of the page, the hero if there
+    /// is one, leaving each shelf where it was. False if already there
+    pub fn on_back(&mut self) -> bool {
+        self.hold_hero();
+        let at_top = match self.hero.as_ref() {
+            Some(hero) => hero.is_focused(),
+            None => self.selected_row == 0,
+        };
+        if at_top {
+            return false;
+        }
+
+        if let Some(shelf) = self.shelves.get_mut(self.selected_row) {
+            shelf.unselect();
+        }
+        self.selected_row = 0;
+        match self.hero.as_mut() {
+            Some(hero) => hero.focus(),
+            None => {
+                if let Some(shelf) = self.shelves.get_mut(0) {
+                    shelf.select();
+                }
+            }
+        }
+        self.scroll_to_selected();
+        true
+    }
+
+    /// The item on the focused tile or hero, if any
+    pub fn selected_item(&self) -> Option<&ContentSetItem> {
+        if let Some(hero) = self.hero.as_ref().filter(|hero| hero.is_focused()) {
+            return Some(hero.selected_item());
+        }
+        self.shelves
+            .get(self.selected_row)
+            .and_then(|shelf| shelf.selected_item())
+    }
+
+    /// Advances the selected shelf, returns a request
+    /// for the next page of the shelf when it is needed
+    pub fn on_key_right(&mut self) -> Option<PageRequest> {
+        self.hold_hero();
+        if let Some(hero) = self.hero.as_mut().filter(|hero| hero.is_focused()) {
+            hero.on_key_right();
+            return None;
+        }
+
         // advance shelf to the next tile
-        self.shelves[self.selected_row].on_key_right();
+        let selected_row = self.selected_row;
+        self.shelves
+            .get_mut(selected_row)
+            .and_then(|shelf| shelf.on_key_right(selected_row))
+    }
+
+    /// A page requested by [`HomePage::on_key_right`] has loaded
+    pub fn on_page_load(&mut self, shelf_index: usize, content_set: Option<ContentSet>) {
+        if let Some(shelf) = self.shelves.get_mut(shelf_index) {
+            let first = shelf.tile_count();
+            shelf.on_page_load(content_set);
+            self.index_tiles(shelf_index, first);
+        }
     }
 
     pub fn on_key_left(&mut self) {
+        self.hold_hero();
+        if let Some(hero) = self.hero.as_mut().filter(|hero| hero.is_focused()) {
+            hero.on_key_left();
+            return;
+        }
+
         // scroll the shelf to the previous tile
-        self.shelves[self.selected_row].on_key_left();
+        if let Some(shelf) = self.shelves.get_mut(self.selected_row) {
+            shelf.on_key_left();
+        }
+    }
+
+    /// Jumps the selected shelf a screen's worth of tiles to the right,
+    /// returns a request for the next page of the shelf when it is needed
+    pub fn on_page_right(&mut self) -> Option<PageRequest> {
+        // the hero moves one program at a time
+        if self.hero_focused() {
+            return self.on_key_right();
+        }
+        // only the first step near the end of the shelf requests a page,
+        // further steps see the page is already loading
+        (0..self.tiles_on_screen())
+            .filter_map(|_| self.on_key_right())
+            .last()
+    }
+
+    /// Jumps the selected shelf a screen's worth of tiles to the left
+    pub fn on_page_left(&mut self) {
+        if self.hero_focused() {
+            return self.on_key_left();
+        }
+        for _ in 0..self.tiles_on_screen() {
+            self.on_key_left();
+        }
+    }
+
+    /// The selected program in the hero, or row and tile
+    pub fn focus(&self) -> Focus {
+        if let Some(hero) = self.hero.as_ref().filter(|hero| hero.is_focused()) {
+            return Focus::Hero(hero.current());
+        }
+        let tile = self
+            .shelves
+            .get(self.selected_row)
+            .map(|shelf| shelf.selected_tile())
+            .unwrap_or(0);
+        Focus::Tile {
+            row: self.selected_row,
+            tile,
+        }
+    }
+
+    /// The focus and the tiles on screen, so the tile images
+    /// can be loaded in order of importance
+    pub fn viewport(&self) -> Viewport {
+        let focus = match self.focus() {
+            Focus::Hero(_) => self.shelves.get(self.selected_row).map(|shelf| TileRef {
+                shelf: self.selected_row,
+                tile: shelf.selected_tile(),
+            }),
+            Focus::Tile { row, tile } => Some(TileRef { shelf: row, tile }),
+        };
+        let height = self.layout.height as i32;
+        let visible = self
+            .shelves
+            .iter()
+            .enumerate()
+            .filter(|(row, _)| {
+                self.shelf_rect(*row)
+                    .is_some_and(|rect| rect.bottom() > 0 && rect.top() < height)
+            })
+            .map(|(row, shelf)| (row, shelf.tiles_in_view()))
+            .collect();
+        Viewport { focus, visible }
+    }
+
+    /// Is the point on the hero carousel
+    pub fn hero_at(&self, x: i32, y: i32) -> bool {
+        self.hero
+            .as_ref()
+            .is_some_and(|hero| hero.contains(self.transform(), x, y))
+    }
+
+    /// The row and tile under the point, if any
+    pub fn tile_at(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let transform = self.transform();
+        self.shelves
+            .iter()
+            .enumerate()
+            .find_map(|(row, shelf)| shelf.tile_at(transform, x, y).map(|tile| (row, tile)))
+    }
+
+    /// The pointer moved, the tile under it is selected without scrolling
+    pub fn on_hover(&mut self, x: i32, y: i32) -> Option<PageRequest> {
+        self.hold_hero();
+        if self.hero_at(x, y) {
+            if !self.hero_focused() {
+                self.shelves[self.selected_row].unselect();
+                if let Some(hero) = self.hero.as_mut() {
+                    hero.focus();
+                }
+            }
+            return None;
+        }
+
+        let (row, tile) = self.tile_at(x, y)?;
+        if self.hero_focused() {
+            self.selected_row = row;
+            self.unfocus_hero();
+        } else if row != self.selected_row {
+            self.shelves[self.selected_row].unselect();
+            self.selected_row = row;
+            self.shelves[row].select();
+        }
+        self.shelves[row].focus(tile, row)
+    }
+
+    /// The wheel or trackpad scrolled, vertically by a row per notch,
+    /// horizontally by adding momentum to the shelf under the pointer
+    pub fn on_wheel(&mut self, y: i32, dx: f32, dy: f32) {
+        self.hold_hero();
+        self.wheel_rows += dy;
+        while self.wheel_rows >= 1.0 {
+            self.wheel_rows -= 1.0;
+            self.on_key_down();
+        }
+        while self.wheel_rows <= -1.0 {
+            self.wheel_rows += 1.0;
+            self.on_key_up();
+        }
+
+        if dx != 0.0 {
+            let row = self.shelf_at(y).unwrap_or(self.selected_row);
+            if let Some(shelf) = self.shelves.get_mut(row) {
+                // scrolling right moves the tiles left
+                shelf.fling(-dx * WHEEL_NOTCH_VELOCITY);
+            }
+        }
+    }
+
+    /// A touch moved, sideways drags the shelf under it,
+    /// up and down moves a row for each shelf dragged past
+    pub fn on_drag(&mut self, y: i32, dx: f32, dy: f32) -> Option<PageRequest> {
+        self.hold_hero();
+        if dx.abs() >= dy.abs() {
+            let row = self.shelf_at(y)?;
+            return self.shelves[row].drag(dx as i32, row);
+        }
+
+        // dragging up scrolls down
+        self.drag_y += dy;
+        let step = self.shelf_step() as f32;
+        while self.drag_y <= -step {
+            self.drag_y += step;
+            self.on_key_down();
+        }
+        while self.drag_y >= step {
+            self.drag_y -= step;
+            self.on_key_up();
+        }
+        None
+    }
+
+    /// A touch was lifted while moving, the shelf under it keeps moving
+    pub fn on_fling(&mut self, y: i32, velocity_x: f32) {
+        self.hold_hero();
+        self.drag_y = 0.0;
+        if let Some(row) = self.shelf_at(y) {
+            self.shelves[row].fling(velocity_x);
+        }
+    }
+
+    /// Is anything still moving, see [`HomePage::tick`]
+    pub fn is_animating(&self) -> bool {
+        self.scroll.is_active()
+            || self.backdrop.is_animating()
+            || self.hero.as_ref().is_some_and(|hero| hero.is_animating())
+            || self.shelves.iter().any(|shelf| shelf.is_animating())
+    }
+
+    /// Seconds until the hero moves on by itself, the page needs
+    /// a tick then even if nothing is animating
+    pub fn time_to_next_tick(&self) -> Option<f32> {
+        self.hero.as_ref().and_then(|hero| hero.time_to_advance())
+    }
+
+    /// Moves the animations along by `seconds`, returns requests for
+    /// the next page of any shelf that has scrolled to its end
+    pub fn tick(&mut self, seconds: f32) -> Vec<PageRequest> {
+        self.scroll.tick(seconds);
+        self.backdrop.tick(seconds);
+        if let Some(hero) = self.hero.as_mut() {
+            hero.tick(seconds);
+        }
+        self.shelves
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(row, shelf)| shelf.tick(seconds, row))
+            .collect()
+    }
+
+    /// The shelf at a height on the screen, from the top of
+    /// its label to the top of the next shelf
+    fn shelf_at(&self, y: i32) -> Option<usize> {
+        let (_, y) = self.transform().unapply(0, y);
+        let step = self.shelf_step();
+        self.shelves
+            .iter()
+            .position(|shelf| y >= shelf.y() && y < shelf.y() + step)
+    }
+
+    fn tiles_on_screen(&self) -> usize {
+        self.shelves
+            .get(self.selected_row)
+            .map(|shelf| shelf.tiles_on_screen(self.layout.width))
+            .unwrap_or(1)
     }
 }
@@ -0,0 +1,42 @@
+use crate::config::Config;
+
+/// The configured sizes are for a 1920x1080 window,
+/// other resolutions scale them to fit
+const REFERENCE_WIDTH: f32 = 1920.0;
+const REFERENCE_HEIGHT: f32 = 1080.0;
+
+/// Sizes for the current window, in pixels
+///
+/// Derived from the config and the drawable size of the window,
+/// which on a high dpi display is larger than the window size,
+/// so everything scales with the resolution and the display dpi.
+/// Recomputed whenever the window is resized
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    pub shelf_padding: u32,
+    pub shelf_height: u32,
+    pub tile_padding: u32,
+    pub font_size: u16,
+}
+impl Layout {
+    /// The layout for a window with a drawable area of `width` x `height` pixels
+    pub fn new(config: &Config, width: u32, height: u32) -> Layout {
+        // scale to whichever side is tighter, so shelves fit on
+        // narrow windows as well as short ones
+        let scale = (width as f32 / REFERENCE_WIDTH).min(height as f32 / REFERENCE_HEIGHT);
+        let scaled = |size: u32| ((size as f32 * scale).round() as u32).max(1);
+
+        let tile_padding = scaled(config.tile_padding);
+        Layout {
+            width,
+            height,
+            shelf_padding: scaled(config.shelf_padding),
+            // tiles fill the shelf below their padding, so the shelf must be taller
+            shelf_height: scaled(config.shelf_height).max(tile_padding + 1),
+            tile_padding,
+            font_size: scaled(config.font_size as u32).min(u16::MAX as u32) as u16,
+        }
+    }
+}
@@ -1,3 +1,18 @@
+//! The pages and components of the home screen
+//!
+//! Components draw onto any SDL `RenderTarget`, the app window
+//! or an offscreen surface, see `headless`, which is built for
+//! tests with the `headless` feature
+pub mod animation;
+pub mod backdrop;
+pub mod details_page;
+#[cfg(feature = "headless")]
+pub mod headless;
+pub mod hero;
 pub mod home_page;
+pub mod layout;
+pub mod render;
+pub mod scene;
 pub mod shelf;
+pub mod texture_cache;
 pub mod tile;
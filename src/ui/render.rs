@@ -0,0 +1,29 @@
+//! Drawing that never panics
+//!
+//! Drawing can fail on bad content, e.g. a corrupt image from the cdn
+//! or text the font can't render, or when the renderer itself has a
+//! problem. Either way the frame is drawn without the part that failed,
+//! the first failure is logged and the rest are counted
+//!
+//! Images and text that fail are remembered by the [`TextureCache`],
+//! so they are only tried once and their component draws a fallback
+//!
+//! [`TextureCache`]: crate::ui::texture_cache::TextureCache
+use log::warn;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static FAILURES: AtomicU64 = AtomicU64::new(0);
+
+/// Reports a failed draw call, e.g. `report(canvas.fill_rect(rect))`
+pub fn report(result: Result<(), String>) {
+    if let Err(e) = result {
+        if FAILURES.fetch_add(1, Ordering::Relaxed) == 0 {
+            warn!("Drawing failed, further failures are only counted: {}", e);
+        }
+    }
+}
+
+/// Draw calls that have failed since startup
+pub fn failures() -> u64 {
+    FAILURES.load(Ordering::Relaxed)
+}
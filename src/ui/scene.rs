@@ -0,0 +1,42 @@
+//! Where components are on screen
+//!
+//! Each component is laid out once, in its own coordinates, when it is
+//! loaded or the window is resized. Scrolling only changes an offset,
+//! and the renderer moves each component onto the screen by the offsets
+//! of everything it is inside with a [`Transform`]
+//!
+//! - the home page lays shelves out top to bottom, scrolled by a y offset
+//! - a shelf lays tiles out left to right below its label, scrolled by an x offset
+use sdl2::rect::Rect;
+
+/// Moves a component's own coordinates onto the screen
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Transform {
+    pub dx: i32,
+    pub dy: i32,
+}
+impl Transform {
+    pub fn new(dx: i32, dy: i32) -> Transform {
+        Transform { dx, dy }
+    }
+
+    /// The transform for a child placed at `dx`, `dy` within this one
+    pub fn then(self, dx: i32, dy: i32) -> Transform {
+        Transform::new(self.dx + dx, self.dy + dy)
+    }
+
+    /// A rect in the component's coordinates, on screen
+    pub fn apply(self, rect: Rect) -> Rect {
+        Rect::new(
+            rect.x() + self.dx,
+            rect.y() + self.dy,
+            rect.width(),
+            rect.height(),
+        )
+    }
+
+    /// A point on screen, in the component's coordinates
+    pub fn unapply(self, x: i32, y: i32) -> (i32, i32) {
+        (x - self.dx, y - self.dy)
+    }
+}
@@ -1,12 +1,36 @@
-use crate::event::ImageLoadEvent;
-use crate::model::home::ContentSet;
+use crate::model::home::{ContentSet, ContentSetItem};
+use crate::model::style::TileShape;
+use crate::ui::animation::{Timing, Tween};
+use crate::ui::layout::Layout;
+use crate::ui::render::report;
+use crate::ui::scene::Transform;
+use crate::ui::texture_cache::TextureCache;
 use crate::ui::tile::ShelfTile;
-use log::warn;
+use bytes::Bytes;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::{Canvas, TextureQuery};
+use sdl2::render::{Canvas, RenderTarget, TextureQuery};
 use sdl2::ttf::Font;
-use sdl2::video::Window;
+use std::ops::Range;
+
+/// Start loading the next page when the selection
+/// is this many tiles from the end of the shelf
+const PAGE_AHEAD: usize = 3;
+
+/// Momentum left after a second of scrolling, per second
+const FRICTION: f32 = 0.05;
+
+/// Momentum stops below this many pixels a second
+const STOP_VELOCITY: f32 = 20.0;
+
+/// A request for the next page of items on a shelf
+pub struct PageRequest {
+    pub shelf_index: usize,
+    pub set_id: String,
+    pub offset: usize,
+    pub page_size: usize,
+    pub style: Option<String>,
+}
 
 /// Represents a "shelf", or a row of tile images
 /// that a user can scroll left and right through
@@ -20,87 +44,340 @@ pub struct Shelf {
     tiles: Vec<ShelfTile>,
     selected_tile: usize,
     padding: u32,
+    // the shelf on the home page, before the home page scrolls
     rect: Rect,
     content_set: ContentSet,
+    shape: TileShape,
+    // the preferred image variant for standard tiles
+    image_size: String,
     label_height: Option<u32>,
+    // the total number of items in the set, more than
+    // the number of tiles until all pages are loaded
+    hits: usize,
+    page_size: usize,
+    loading_page: bool,
+    timing: Timing,
+    // how far the tiles are scrolled to the left, in pixels,
+    // the tiles themselves stay where they were laid out
+    scroll: Tween,
+    // horizontal momentum from a fling or the wheel, in pixels a second
+    velocity: f32,
 }
 impl Shelf {
     /// Loads a Shelf
     /// Loads tiles that live on the shelf
-    pub fn load(content_set: ContentSet, height: u32, tile_padding: u32, y: i32) -> Shelf {
+    /// Tiles are shaped by the style of the content set, see [`TileShape`]
+    pub fn load(
+        content_set: ContentSet,
+        layout: &Layout,
+        y: i32,
+        image_size: &str,
+        timing: Timing,
+    ) -> Shelf {
         let mut shelf = Shelf {
             title: content_set.title().clone(),
-            height,
+            hits: content_set.hits(),
+            page_size: content_set.page_size(),
+            loading_page: false,
+            timing,
+            scroll: Tween::new(0.0),
+            velocity: 0.0,
+            height: layout.shelf_height,
             selected: false,
             tiles: Vec::new(),
             selected_tile: 0,
-            padding: tile_padding,
-            rect: Rect::new(tile_padding as i32, y, 1920, height),
+            padding: layout.tile_padding,
+            rect: Rect::new(
+                layout.tile_padding as i32,
+                y,
+                layout.width,
+                layout.shelf_height,
+            ),
+            shape: content_set.tile_shape(),
+            image_size: image_size.to_string(),
             content_set,
             label_height: None,
         };
-        shelf.load_tiles();
+        let items = shelf.content_set.items_iter().cloned().collect();
+        shelf.add_tiles(items);
         shelf
     }
 
-    fn load_tiles(&mut self) {
+    fn add_tiles(&mut self, items: Vec<ContentSetItem>) {
         // load the items as tiles, from left to right
-        // starting at an initial offset
-        let mut x_pos = self.padding as i32;
-        let y_pos = self.y() + self.padding as i32;
-
-        for item in self.content_set.items_iter() {
-            let tile = ShelfTile::load(
-                item.clone(),
-                x_pos,
-                y_pos,
-                self.height() - self.padding as u32,
+        // after the last tile
+        let first_index = self.tiles.len();
+        let image_sizes = self.shape.image_sizes(&self.image_size);
+
+        for (index, item) in items.into_iter().enumerate() {
+            let mut tile = ShelfTile::load(
+                item,
+                0,
+                self.height() - self.padding,
+                self.shape.aspect_ratio(&self.image_size),
+                &image_sizes,
             );
-            x_pos = x_pos + tile.width() as i32 + self.padding as i32;
+            tile.set_x(self.tile_x(&tile, first_index + index));
             self.tiles.push(tile);
         }
     }
 
-    pub fn on_image_load(&mut self, event: ImageLoadEvent) {
-        // find the tile matching the url and update
-        let maybe_found_tile = self
-            .tiles
-            .iter_mut()
-            .find(|tile| tile.image_url() == &event.img_url);
+    /// Resizes the shelf and its tiles for a new layout, moving
+    /// the shelf to `y` and keeping the selected tile in view
+    pub fn on_resize(&mut self, layout: &Layout, y: i32) {
+        self.height = layout.shelf_height;
+        self.padding = layout.tile_padding;
+        self.rect = Rect::new(self.padding as i32, y, layout.width, self.height);
+        // the label is measured again when drawn, the font size may have changed
+        self.label_height = None;
 
-        if let Some(tile) = maybe_found_tile {
-            tile.set_img(event.bytes);
+        let tile_height = self.height - self.padding;
+        for index in 0..self.tiles.len() {
+            self.tiles[index].resize(tile_height);
+            let x = self.tile_x(&self.tiles[index], index);
+            self.tiles[index].set_x(x);
+        }
+
+        self.velocity = 0.0;
+        self.scroll.jump_to(self.selected_scroll());
+    }
+
+    /// The left of the tile at `index`, tiles line up from the left
+    fn tile_x(&self, tile: &ShelfTile, index: usize) -> i32 {
+        let step = tile.default_size().0 as i32 + self.padding as i32;
+        self.padding as i32 + index as i32 * step
+    }
+
+    /// The width of a tile on this shelf, they are all the same shape
+    fn tile_width(&self) -> i32 {
+        self.tiles
+            .first()
+            .map(|tile| tile.default_size().0 as i32)
+            .unwrap_or(0)
+    }
+
+    /// The distance from the left of one tile to the next
+    fn tile_step(&self) -> i32 {
+        self.tile_width() + self.padding as i32
+    }
+
+    /// The tiles that could be on screen at the current scroll, a tile
+    /// either side is included as the selected tile grows into view
+    pub fn tiles_in_view(&self) -> Range<usize> {
+        let step = self.tile_step().max(1);
+        let scroll = self.scroll.value().round() as i32;
+        let first = (scroll / step - 1).max(0) as usize;
+        let last = ((scroll + self.rect.width() as i32) / step + 2).max(0) as usize;
+        first.min(self.tiles.len())..last.min(self.tiles.len())
+    }
+
+    /// Moves the tiles, in the shelf's coordinates, onto the screen
+    fn tiles_transform(&self, page: Transform) -> Transform {
+        page.then(
+            -(self.scroll.value().round() as i32),
+            self.rect.y() + self.tile_y(),
+        )
+    }
+
+    /// How far the tiles can scroll, the last tile lines up with
+    /// the right edge and a shelf that fits on screen does not scroll
+    fn max_scroll(&self) -> f32 {
+        (self.tiles.len() as i32 * self.tile_step() - self.rect.width() as i32).max(0) as f32
+    }
+
+    /// Where the selected tile is in view, the tiles before it have
+    /// scrolled off to the left with the one before it half in view
+    fn selected_scroll(&self) -> f32 {
+        let scroll = match self.selected_tile {
+            0 => 0,
+            selected => selected as i32 * self.tile_step() - self.tile_width() / 2,
+        };
+        (scroll as f32).clamp(0.0, self.max_scroll())
+    }
+
+    /// Scrolls the selected tile into view, over the scroll
+    /// animation and stopping any momentum. The selection may
+    /// have moved by pointer since the last key press
+    fn scroll_to_selected(&mut self) {
+        self.velocity = 0.0;
+        self.scroll.animate_to(
+            self.selected_scroll(),
+            self.timing.scroll,
+            self.timing.easing,
+        );
+    }
+
+    /// Requests the next page of items if the selection
+    /// is nearing the end of the loaded tiles
+    fn next_page(&mut self, shelf_index: usize) -> Option<PageRequest> {
+        if self.selected_tile + PAGE_AHEAD >= self.tiles.len() {
+            self.request_page(shelf_index)
         } else {
-            warn!("Unable to find tile for image {:?}", event.img_url);
+            None
+        }
+    }
+
+    /// Requests the next page of items if the end of the
+    /// shelf has been scrolled into view
+    fn next_page_in_view(&mut self, shelf_index: usize) -> Option<PageRequest> {
+        let scroll = self.scroll.value().round() as i32;
+        let end_in_view = self
+            .tiles
+            .last()
+            .is_some_and(|last| last.x() - scroll < self.rect.right());
+        if end_in_view {
+            self.request_page(shelf_index)
+        } else {
+            None
+        }
+    }
+
+    fn request_page(&mut self, shelf_index: usize) -> Option<PageRequest> {
+        if self.loading_page || self.tiles.len() >= self.hits {
+            return None;
+        }
+        let set_id = self.content_set.set_id()?.clone();
+        self.loading_page = true;
+        Some(PageRequest {
+            shelf_index,
+            set_id,
+            offset: self.tiles.len(),
+            page_size: self.page_size.max(1),
+            style: self.content_set.style().cloned(),
+        })
+    }
+
+    /// Appends the tiles for a page of items, a failed
+    /// or empty page ends paging for this shelf
+    pub fn on_page_load(&mut self, content_set: Option<ContentSet>) {
+        self.loading_page = false;
+        match content_set {
+            Some(cs) if cs.items_iter().next().is_some() => self.add_tiles(cs.items()),
+            _ => self.hits = self.tiles.len(),
+        }
+    }
+
+    /// Gives a tile its image, false if the tile does not show that image
+    pub fn set_image(&mut self, index: usize, img_url: &String, bytes: &Bytes) -> bool {
+        match self.tiles.get_mut(index) {
+            Some(tile) if tile.image_url() == Some(img_url) => {
+                tile.set_img(bytes.clone());
+                true
+            }
+            _ => false,
         }
     }
 
-    pub fn draw(&mut self, font: &Font, canvas: &mut Canvas<Window>) {
+    pub fn tile_image_url(&self, index: usize) -> Option<&String> {
+        self.tiles.get(index)?.image_url()
+    }
+
+    /// Does a tile have its image yet
+    pub fn has_image(&self, index: usize) -> bool {
+        self.tiles.get(index).is_some_and(|tile| tile.has_img())
+    }
+
+    /// The images of the tiles from `first` on, by tile index
+    pub fn image_urls(&self, first: usize) -> impl Iterator<Item = (usize, &String)> {
+        self.tiles
+            .iter()
+            .enumerate()
+            .skip(first)
+            .filter_map(|(index, tile)| tile.image_url().map(|url| (index, url)))
+    }
+
+    /// Draws the shelf, moved onto the screen by the home page's transform
+    pub fn draw<T: RenderTarget>(
+        &mut self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        page: Transform,
+    ) {
         // only draw if we are within view
         let viewport = canvas.viewport();
-        if self.y() <= viewport.bottom() && self.bottom() >= viewport.top() {
-            self.draw_label(font, canvas);
-            canvas.draw_rect(self.rect).unwrap();
+        let rect = page.apply(self.rect);
+        if rect.top() <= viewport.bottom() && rect.bottom() >= viewport.top() {
+            self.draw_label(font, canvas, textures, rect);
+            report(canvas.draw_rect(rect));
 
-            if self.selected {
-                // draw the selected one last so it appears above the others
-                self.tiles
-                    .iter()
-                    .filter(|t| !t.selected())
-                    .for_each(|tile| tile.draw(canvas));
-
-                // draw the selected tile last so it is on top
-                self.tiles[self.selected_tile].draw(canvas);
-            } else {
-                self.tiles.iter().for_each(|tile| tile.draw(canvas));
+            // only the tiles around the scroll are drawn, the selected
+            // one last so it appears above the others
+            let transform = self.tiles_transform(page);
+            let in_view = self.tiles_in_view();
+            self.tiles[in_view.clone()]
+                .iter()
+                .filter(|t| !(self.selected && t.selected()))
+                .for_each(|tile| tile.draw(font, canvas, textures, transform));
+            if self.selected && in_view.contains(&self.selected_tile) {
+                self.tiles[self.selected_tile].draw(font, canvas, textures, transform);
             }
+
+            if self.loading_page {
+                self.draw_loading(font, canvas, textures, transform);
+            }
+        }
+    }
+
+    /// Draws a placeholder after the last tile
+    /// while the next page is loading
+    fn draw_loading<T: RenderTarget>(
+        &self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        transform: Transform,
+    ) {
+        let last = match self.tiles.last() {
+            Some(last) => last,
+            None => return,
+        };
+        let (width, height) = last.default_size();
+        let rect = transform.apply(Rect::new(
+            last.right() + self.padding as i32,
+            0,
+            width,
+            height,
+        ));
+        if rect.left() > canvas.viewport().right() {
+            return;
         }
+
+        canvas.set_draw_color(Color::GREY);
+        report(canvas.draw_rect(rect));
+        canvas.set_draw_color(Color::BLACK);
+
+        let texture = match textures.text(font, "Loading...") {
+            Ok(texture) => texture,
+            Err(_) => return,
+        };
+        let TextureQuery {
+            width: text_width,
+            height: text_height,
+            ..
+        } = texture.query();
+        let text_rect = Rect::new(
+            rect.center().x() - text_width as i32 / 2,
+            rect.center().y() - text_height as i32 / 2,
+            text_width,
+            text_height,
+        );
+        report(canvas.copy(texture, None, Some(text_rect)));
     }
 
-    fn draw_label(&mut self, font: &Font, canvas: &mut Canvas<Window>) {
-        let surface = font.render(&self.title).blended(Color::WHITE).unwrap();
-        let tc = canvas.texture_creator();
-        let texture = tc.create_texture_from_surface(&surface).unwrap();
+    fn draw_label<T: RenderTarget>(
+        &mut self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        rect: Rect,
+    ) {
+        // the label is only rendered the first time it is drawn, a label
+        // that can't be, e.g. an empty title, leaves the default space
+        let texture = match textures.text(font, &self.title) {
+            Ok(texture) => texture,
+            Err(_) => return,
+        };
 
         // Determine the size of the text
         let TextureQuery {
@@ -109,42 +386,21 @@ impl Shelf {
             ..
         } = texture.query();
 
-        // Size the title rect and copy the font onto it
+        // the tiles sit below the label, once it is
+        // drawn we know its exact height
         self.label_height = Some(text_height);
-
-        // We need to make sure the tiles stay below the shelf label
-        // so reset the tiles y position once the label is set
-        // and we know the exact height
-        self.reset_tile_y();
-        let title_rect = Rect::new(self.x(), self.y(), text_width, text_height);
-        canvas.copy(&texture, None, Some(title_rect)).unwrap();
+        let title_rect = Rect::new(rect.x(), rect.y(), text_width, text_height);
+        report(canvas.copy(texture, None, Some(title_rect)));
     }
 
-    /// Update the y position for this shelf
-    /// Do not forget to update the y position for all tiles as well
-    pub fn set_y(&mut self, new_y: i32) {
-        self.rect.set_y(new_y);
-        self.reset_tile_y();
+    /// The top of the tiles, below the label
+    fn tile_y(&self) -> i32 {
+        self.label_height.map(|lh| lh as i32).unwrap_or(0) + 10
     }
 
-    /// Resets the y position for all tiles
-    fn reset_tile_y(&mut self) {
-        let new_y = self.y();
-        let tile_y = self
-            .label_height
-            .map(|lh| lh as i32 + new_y)
-            .unwrap_or(new_y);
-        self.tiles
-            .iter_mut()
-            .for_each(|tile| tile.set_y(tile_y + 10));
-    }
-
-    pub fn bottom(&self) -> i32 {
-        self.rect.bottom()
-    }
-
-    pub fn x(&self) -> i32 {
-        self.rect.x()
+    /// The shelf on the home page, before the home page scrolls
+    pub fn rect(&self) -> Rect {
+        self.rect
     }
 
     pub fn y(&self) -> i32 {
@@ -157,47 +413,151 @@ impl Shelf {
 
     pub fn select(&mut self) {
         self.selected = true;
-        self.tiles[self.selected_tile].select();
+        if let Some(tile) = self.tiles.get_mut(self.selected_tile) {
+            tile.select(&self.timing);
+        }
     }
 
     pub fn unselect(&mut self) {
         self.selected = false;
-        self.tiles[self.selected_tile].unselect();
+        if let Some(tile) = self.tiles.get_mut(self.selected_tile) {
+            tile.unselect(&self.timing);
+        }
     }
 
     pub fn title(&self) -> &String {
         &self.title
     }
 
-    pub fn on_key_right(&mut self) {
+    /// The item on the selected tile, if any
+    pub fn selected_item(&self) -> Option<&ContentSetItem> {
+        self.item(self.selected_tile)
+    }
+
+    pub fn item(&self, index: usize) -> Option<&ContentSetItem> {
+        self.tiles.get(index).map(|tile| tile.item())
+    }
+
+    /// The tile under a point on screen, if any
+    pub fn tile_at(&self, page: Transform, x: i32, y: i32) -> Option<usize> {
+        let (x, y) = self.tiles_transform(page).unapply(x, y);
+        self.tiles_in_view()
+            .find(|index| self.tiles[*index].contains(x, y))
+    }
+
+    /// Where a tile is on screen, including any growth from being selected
+    pub fn tile_rect(&self, page: Transform, index: usize) -> Option<Rect> {
+        let tile = self.tiles.get(index)?;
+        Some(self.tiles_transform(page).apply(tile.rect()))
+    }
+
+    /// Moves the selection to a tile without scrolling, as
+    /// the pointer is already over it
+    pub fn focus(&mut self, index: usize, shelf_index: usize) -> Option<PageRequest> {
+        if index >= self.tiles.len() {
+            return None;
+        }
+        if index != self.selected_tile {
+            self.tiles[self.selected_tile].unselect(&self.timing);
+            self.selected_tile = index;
+            if self.selected {
+                self.tiles[index].select(&self.timing);
+            }
+        }
+        self.next_page(shelf_index)
+    }
+
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn selected_tile(&self) -> usize {
+        self.selected_tile
+    }
+
+    /// Drags the tiles along by `dx` pixels, stopping any momentum
+    pub fn drag(&mut self, dx: i32, shelf_index: usize) -> Option<PageRequest> {
+        self.velocity = 0.0;
+        self.scroll_by(dx as f32);
+        self.next_page_in_view(shelf_index)
+    }
+
+    /// Adds momentum, in pixels a second, positive moves the tiles right
+    pub fn fling(&mut self, velocity: f32) {
+        // momentum takes over from wherever a key press was scrolling to
+        self.scroll.stop();
+        self.velocity += velocity;
+    }
+
+    /// Is the shelf scrolling, or a tile growing or shrinking
+    pub fn is_animating(&self) -> bool {
+        self.velocity != 0.0
+            || self.scroll.is_active()
+            || self.tiles.iter().any(|tile| tile.is_animating())
+    }
+
+    /// Moves the animations along, and the tiles by their momentum,
+    /// slowing down as they go
+    pub fn tick(&mut self, seconds: f32, shelf_index: usize) -> Option<PageRequest> {
+        self.tiles.iter_mut().for_each(|tile| tile.tick(seconds));
+
+        let scrolling = self.velocity != 0.0 || self.scroll.is_active();
+        self.scroll.tick(seconds);
+        if self.velocity != 0.0 {
+            let distance = self.velocity * seconds;
+            let moved = self.scroll_by(distance);
+            self.velocity *= FRICTION.powf(seconds);
+            if (moved - distance).abs() > 0.5 || self.velocity.abs() < STOP_VELOCITY {
+                // stopped at the end of the shelf, or slowed to a stop
+                self.velocity = 0.0;
+            }
+        }
+        if scrolling {
+            self.next_page_in_view(shelf_index)
+        } else {
+            None
+        }
+    }
+
+    /// Moves every tile by `dx` pixels, keeping the first tile from
+    /// leaving the left edge and the last tile from leaving the right
+    /// edge. Returns how far the tiles actually moved
+    fn scroll_by(&mut self, dx: f32) -> f32 {
+        let scroll = self.scroll.value();
+        let scrolled = (scroll - dx).clamp(0.0, self.max_scroll());
+        self.scroll.jump_to(scrolled);
+        scroll - scrolled
+    }
+
+    /// How many tiles fit across a window of the given width
+    pub fn tiles_on_screen(&self, width: u32) -> usize {
+        let tile_width = self
+            .tiles
+            .first()
+            .map(|tile| tile.default_size().0)
+            .unwrap_or(width);
+        (width / (tile_width + self.padding).max(1)).max(1) as usize
+    }
+
+    /// Moves to the next tile, returns a request
+    /// for the next page when nearing the end
+    pub fn on_key_right(&mut self, shelf_index: usize) -> Option<PageRequest> {
         // only move to new tile if this row is selected
         // safeguard
         if self.selected {
             // if we are at the end already, don't do anything
-            if self.selected_tile < self.tiles.len() - 1 {
+            if self.selected_tile + 1 < self.tiles.len() {
                 let current_selection = self.selected_tile;
                 self.selected_tile += 1;
-                self.tiles[current_selection].unselect();
+                self.tiles[current_selection].unselect(&self.timing);
 
                 // move all tiles to the left
-                if current_selection == 0 {
-                    // shift left everything by half
-                    // since this is the first tile
-                    for tile in self.tiles.iter_mut() {
-                        tile.set_x(tile.x() - (tile.width() as i32 / 2) - self.padding as i32)
-                    }
-                } else {
-                    // shift everything left by a full tile
-                    let mut prior_x = 0 - self.tiles[0].width() as i32 / 2 - self.padding as i32;
-                    for tile in self.tiles.iter_mut() {
-                        let next_x = tile.x();
-                        tile.set_x(prior_x);
-                        prior_x = next_x;
-                    }
-                }
-                self.tiles[self.selected_tile].select();
+                self.scroll_to_selected();
+                self.tiles[self.selected_tile].select(&self.timing);
             }
+            return self.next_page(shelf_index);
         }
+        None
     }
 
     pub fn on_key_left(&mut self) {
@@ -207,24 +567,11 @@ impl Shelf {
             if self.selected_tile > 0 {
                 let current_selection = self.selected_tile;
                 self.selected_tile -= 1;
-                self.tiles[current_selection].unselect();
-
-                // if we are going to 0, then we shift by a smaller amount
-                if self.selected_tile == 0 {
-                    // shift left everything by half
-                    for tile in self.tiles.iter_mut() {
-                        tile.set_x(tile.x() + (tile.width() as i32 / 2) + self.padding as i32)
-                    }
-                } else {
-                    let mut prior_x =
-                        self.tiles[self.tiles.len() - 1].right() + self.padding as i32;
-                    for tile in self.tiles.iter_mut().rev() {
-                        let next_x = tile.x();
-                        tile.set_x(prior_x);
-                        prior_x = next_x;
-                    }
-                }
-                self.tiles[self.selected_tile].select();
+                self.tiles[current_selection].unselect(&self.timing);
+
+                // move all tiles back to the right
+                self.scroll_to_selected();
+                self.tiles[self.selected_tile].select(&self.timing);
             }
         }
     }
@@ -0,0 +1,197 @@
+use anyhow::{anyhow, Error, Result};
+use log::{debug, warn};
+use sdl2::image::LoadTexture;
+use sdl2::pixels::Color;
+use sdl2::render::{RenderTarget, Texture, TextureCreator};
+use sdl2::ttf::Font;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum TextureKey {
+    /// a decoded tile image, keyed by image url
+    Image(String),
+    /// rendered text, keyed by the text itself
+    Text(String),
+}
+
+impl fmt::Display for TextureKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextureKey::Image(url) => write!(f, "image {}", url),
+            TextureKey::Text(text) => write!(f, "text {:?}", text),
+        }
+    }
+}
+
+struct CachedTexture<'a> {
+    texture: Texture<'a>,
+    bytes: u64,
+    last_used: u64,
+}
+
+/// Holds decoded textures between frames so images are decoded
+/// and text rendered once, rather than on every draw
+///
+/// Textures live in gpu memory, so the cache has a byte budget.
+/// Once over budget, the least recently drawn textures are evicted
+/// at the end of a frame, textures drawn in the current frame
+/// (those on screen) are never evicted
+///
+/// The cache is tied to the render target its textures are drawn
+/// on, the window or an offscreen surface for screenshots
+///
+/// An image that fails to decode, or text that fails to render, is
+/// logged once and remembered as broken, later draws of it fail
+/// straight away so the caller can draw a fallback
+pub struct TextureCache<'a, T: RenderTarget> {
+    texture_creator: &'a TextureCreator<T::Context>,
+    textures: HashMap<TextureKey, CachedTexture<'a>>,
+    broken: HashSet<TextureKey>,
+    max_bytes: u64,
+    total_bytes: u64,
+    frame: u64,
+}
+impl<'a, T: RenderTarget> TextureCache<'a, T> {
+    pub fn new(texture_creator: &'a TextureCreator<T::Context>, max_bytes: u64) -> Self {
+        TextureCache {
+            texture_creator,
+            textures: HashMap::new(),
+            broken: HashSet::new(),
+            max_bytes,
+            total_bytes: 0,
+            frame: 0,
+        }
+    }
+
+    /// Call before drawing a frame
+    pub fn begin_frame(&mut self) {
+        self.frame += 1;
+    }
+
+    /// Call after drawing a frame, evicts textures if over budget
+    pub fn end_frame(&mut self) {
+        if self.total_bytes <= self.max_bytes {
+            return;
+        }
+
+        let mut stale: Vec<(&TextureKey, u64, u64)> = self
+            .textures
+            .iter()
+            .filter(|(_, cached)| cached.last_used < self.frame)
+            .map(|(key, cached)| (key, cached.last_used, cached.bytes))
+            .collect();
+        stale.sort_by_key(|(_, last_used, _)| *last_used);
+
+        let mut total = self.total_bytes;
+        let evictions: Vec<TextureKey> = stale
+            .into_iter()
+            .take_while(|(_, _, bytes)| {
+                let evict = total > self.max_bytes;
+                total -= bytes;
+                evict
+            })
+            .map(|(key, _, _)| key.clone())
+            .collect();
+
+        debug!("Evicting {} textures", evictions.len());
+        for key in evictions {
+            self.remove(&key);
+        }
+    }
+
+    /// Returns the texture for the image, decoding the bytes if needed
+    ///
+    /// Callers that change the texture, e.g. its alpha, put it back after drawing
+    pub fn image(&mut self, img_url: &str, bytes: &[u8]) -> Result<&mut Texture<'a>> {
+        let key = TextureKey::Image(img_url.to_string());
+        let texture_creator = self.texture_creator;
+        self.load(key, || {
+            texture_creator
+                .load_texture_bytes(bytes)
+                .map_err(Error::msg)
+        })
+    }
+
+    /// Returns the texture for the text, rendering it if needed
+    pub fn text(&mut self, font: &Font, text: &str) -> Result<&mut Texture<'a>> {
+        let key = TextureKey::Text(text.to_string());
+        let texture_creator = self.texture_creator;
+        self.load(key, || {
+            let surface = font.render(text).blended(Color::WHITE)?;
+            Ok(texture_creator.create_texture_from_surface(&surface)?)
+        })
+    }
+
+    /// Images and text that have failed, since startup
+    pub fn failures(&self) -> usize {
+        self.broken.len()
+    }
+
+    /// The cached texture, or a new one from `create`,
+    /// unless it has failed before
+    fn load(
+        &mut self,
+        key: TextureKey,
+        create: impl FnOnce() -> Result<Texture<'a>>,
+    ) -> Result<&mut Texture<'a>> {
+        if self.broken.contains(&key) {
+            return Err(anyhow!("{} is broken", key));
+        }
+        if !self.textures.contains_key(&key) {
+            match create() {
+                Ok(texture) => self.insert(key.clone(), texture),
+                Err(e) => {
+                    warn!("Unable to draw {}: {:?}", key, e);
+                    self.broken.insert(key);
+                    return Err(e);
+                }
+            }
+        }
+        self.use_texture(&key)
+            .ok_or_else(|| anyhow!("{} was not cached", key))
+    }
+
+    /// Drops all rendered text, e.g. once the font size changes
+    pub fn clear_text(&mut self) {
+        let text_keys: Vec<TextureKey> = self
+            .textures
+            .keys()
+            .filter(|key| matches!(key, TextureKey::Text(_)))
+            .cloned()
+            .collect();
+        for key in text_keys {
+            self.remove(&key);
+        }
+        // text that failed at the old size may render at the new one
+        self.broken
+            .retain(|key| !matches!(key, TextureKey::Text(_)));
+    }
+
+    fn use_texture(&mut self, key: &TextureKey) -> Option<&mut Texture<'a>> {
+        let cached = self.textures.get_mut(key)?;
+        cached.last_used = self.frame;
+        Some(&mut cached.texture)
+    }
+
+    fn insert(&mut self, key: TextureKey, texture: Texture<'a>) {
+        let query = texture.query();
+        let bytes = query.width as u64 * query.height as u64 * 4;
+        self.total_bytes += bytes;
+        let cached = CachedTexture {
+            texture,
+            bytes,
+            last_used: self.frame,
+        };
+        // textures free their gpu memory when dropped
+        if let Some(old) = self.textures.insert(key, cached) {
+            self.total_bytes -= old.bytes;
+        }
+    }
+
+    fn remove(&mut self, key: &TextureKey) {
+        if let Some(old) = self.textures.remove(key) {
+            self.total_bytes -= old.bytes;
+        }
+    }
+}
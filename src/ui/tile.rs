@@ -1,142 +1,255 @@
 use crate::model::home::ContentSetItem;
+use crate::ui::animation::{Timing, Tween};
+use crate::ui::render::report;
+use crate::ui::scene::Transform;
+use crate::ui::texture_cache::TextureCache;
 use bytes::Bytes;
-use sdl2::image::LoadTexture;
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::render::{Canvas, RenderTarget, TextureQuery};
+use sdl2::ttf::Font;
 
 /// Represents an individual tile on a shelf
 /// related to some content, a program or a tv series
 pub struct ShelfTile {
+    item: ContentSetItem,
     img_raw: Option<Bytes>,
-    img_url: String,
-    rect: Rect,
+    // None when the item has no artwork, a placeholder is drawn instead
+    img_url: Option<String>,
+    // the left of the tile along its shelf when it is not selected,
+    // a selected tile grows out from its default size on all sides
+    x: i32,
     selected: bool,
+    // how far the tile has grown, from 0 to 1, see grow
+    scale: Tween,
+    aspect_ratio: f32,
     default_width: u32,
     default_height: u32,
 }
 impl ShelfTile {
-    /// Loads a tile
-    pub fn load(item: ContentSetItem, x: i32, y: i32, height: u32) -> ShelfTile {
-        let default_width = (height as f32 * 1.78) as u32;
+    /// Loads a tile of the given width over height, with the first
+    /// of the image sizes that the item has, see
+    /// [`ContentSetItem::tile_image_url`] for the fallbacks
+    pub fn load(
+        item: ContentSetItem,
+        x: i32,
+        height: u32,
+        aspect_ratio: f32,
+        image_sizes: &[&str],
+    ) -> ShelfTile {
+        let default_width = (height as f32 * aspect_ratio) as u32;
         let default_height = height;
-        let img_url = item.tile_image_url("1.78").expect("Missing tile image");
+        let img_url = item.tile_image_url(image_sizes).cloned();
         ShelfTile {
+            item,
             img_raw: None,
-            img_url: img_url.clone(),
-            rect: Rect::new(x, y, default_width, default_height),
+            img_url,
+            x,
             selected: false,
+            scale: Tween::new(0.0),
+            aspect_ratio,
             default_width,
             default_height,
         }
     }
 
-    pub fn draw(&self, canvas: &mut Canvas<Window>) {
+    /// Draws the tile, moved onto the screen by its shelf's transform
+    pub fn draw<T: RenderTarget>(
+        &self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        transform: Transform,
+    ) {
         // only draw if we are in view
         let viewport = canvas.viewport();
-        if self.right() >= 0 && self.left() <= viewport.right() {
-            if let Some(bytes) = &self.img_raw {
-                let texture_creator = canvas.texture_creator();
-                let texture = texture_creator.load_texture_bytes(bytes).unwrap();
-
-                if self.selected {
+        let rect = transform.apply(self.rect());
+        if rect.right() >= 0 && rect.left() <= viewport.right() {
+            match (&self.img_url, &self.img_raw) {
+                (Some(img_url), Some(bytes)) => {
+                    // the image is only decoded the first time it is drawn,
+                    // a broken image shows the title like a tile without artwork
+                    let texture = match textures.image(img_url, bytes) {
+                        Ok(texture) => texture,
+                        Err(_) => return self.draw_placeholder(font, canvas, textures, rect),
+                    };
+                    let TextureQuery { width, height, .. } = texture.query();
+                    let src = crop_to_fit(width, height, rect);
+                    self.draw_selection(canvas, rect);
+                    report(canvas.copy(texture, src, Some(rect)));
+                }
+                (Some(_), None) => {
+                    // Empty, still loading
                     canvas.set_draw_color(Color::WHITE);
-                    canvas
-                        .fill_rect(Rect::new(
-                            self.x() - 5,
-                            self.y() - 5,
-                            self.width() + 10,
-                            self.height() + 10,
-                        ))
-                        .unwrap();
-                    canvas.copy(&texture, None, Some(self.rect)).unwrap();
+                    report(canvas.draw_rect(rect));
                     canvas.set_draw_color(Color::BLACK);
-                } else {
-                    canvas.set_draw_color(Color::BLACK);
-                    canvas.copy(&texture, None, Some(self.rect)).unwrap();
                 }
-            } else {
-                // Empty
-                canvas.set_draw_color(Color::WHITE);
-                canvas.draw_rect(self.rect).unwrap();
-                canvas.set_draw_color(Color::BLACK);
+                (None, _) => self.draw_placeholder(font, canvas, textures, rect),
             }
         }
     }
 
+    /// Outlines the selected tile, the outline fades
+    /// in and out as the tile grows and shrinks
+    fn draw_selection<T: RenderTarget>(&self, canvas: &mut Canvas<T>, rect: Rect) {
+        let scale = self.scale.value();
+        if scale > 0.0 {
+            let shade = (scale.min(1.0) * 255.0).round() as u8;
+            canvas.set_draw_color(Color::RGB(shade, shade, shade));
+            report(canvas.fill_rect(Rect::new(
+                rect.x() - 5,
+                rect.y() - 5,
+                rect.width() + 10,
+                rect.height() + 10,
+            )));
+            canvas.set_draw_color(Color::BLACK);
+        }
+    }
+
+    /// Draws the title in place of artwork, for items that have none
+    fn draw_placeholder<T: RenderTarget>(
+        &self,
+        font: &Font,
+        canvas: &mut Canvas<T>,
+        textures: &mut TextureCache<T>,
+        rect: Rect,
+    ) {
+        self.draw_selection(canvas, rect);
+        canvas.set_draw_color(Color::RGB(40, 40, 48));
+        report(canvas.fill_rect(rect));
+        canvas.set_draw_color(Color::BLACK);
+
+        let title = match self.item.title() {
+            Some(title) if !title.is_empty() => title,
+            _ => return,
+        };
+        let texture = match textures.text(font, title) {
+            Ok(texture) => texture,
+            Err(_) => return,
+        };
+        let TextureQuery { width, height, .. } = texture.query();
+
+        // long titles are cut off at the edges of the tile
+        let margin = 10;
+        let visible_width = width.min(rect.width().saturating_sub(margin * 2)).max(1);
+        let visible_height = height.min(rect.height()).max(1);
+        let src = Rect::new(0, 0, visible_width, visible_height);
+        let dst = Rect::from_center(rect.center(), visible_width, visible_height);
+        report(canvas.copy(texture, src, Some(dst)));
+    }
+
     pub fn set_img(&mut self, bytes: Bytes) {
         self.img_raw = Some(bytes);
     }
 
-    pub fn image_url(&self) -> &String {
-        &self.img_url
+    pub fn has_img(&self) -> bool {
+        self.img_raw.is_some()
     }
 
-    pub fn set_x(&mut self, new_x: i32) {
-        self.rect.set_x(new_x);
+    /// The program this tile represents
+    pub fn item(&self) -> &ContentSetItem {
+        &self.item
     }
 
-    pub fn x(&self) -> i32 {
-        self.rect.x()
+    /// The tile artwork, None if the item has no artwork
+    pub fn image_url(&self) -> Option<&String> {
+        self.img_url.as_ref()
     }
 
-    pub fn set_y(&mut self, new_y: i32) {
-        self.rect.set_y(new_y);
+    /// Where the tile is drawn along its shelf, from the top of
+    /// the tiles, grown by however far it is selected
+    pub fn rect(&self) -> Rect {
+        let scale = self.scale.value();
+        let (grow_x, grow_y) = self.grow();
+        let grow_x = (grow_x as f32 * scale).round() as i32;
+        let grow_y = (grow_y as f32 * scale).round() as i32;
+        Rect::new(
+            self.x - grow_x,
+            -grow_y,
+            (self.default_width as i32 + grow_x * 2).max(1) as u32,
+            (self.default_height as i32 + grow_y * 2).max(1) as u32,
+        )
     }
 
-    pub fn y(&self) -> i32 {
-        self.rect.y()
+    pub fn set_x(&mut self, new_x: i32) {
+        self.x = new_x;
     }
 
-    pub fn left(&self) -> i32 {
-        self.rect.left()
+    /// The left edge, ignoring any scaling from being selected
+    pub fn x(&self) -> i32 {
+        self.x
     }
 
+    /// The right edge, ignoring any scaling from being selected
     pub fn right(&self) -> i32 {
-        self.rect.right()
+        self.x + self.default_width as i32
     }
 
-    pub fn height(&self) -> u32 {
-        self.rect.height()
+    /// Is the point, along the shelf, on this tile
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.rect().contains_point((x, y))
     }
 
-    pub fn width(&self) -> u32 {
-        self.rect.width()
+    /// The size of the tile when it is not selected
+    pub fn default_size(&self) -> (u32, u32) {
+        (self.default_width, self.default_height)
     }
 
     pub fn selected(&self) -> bool {
         self.selected
     }
 
-    pub fn select(&mut self) {
+    /// Grows the tile slightly, over the focus animation
+    pub fn select(&mut self, timing: &Timing) {
         if !self.selected {
             self.selected = true;
-
-            // Scale the image slightly
-            let new_width = self.rect.width() + 36;
-            let new_height = self.rect.height() + 20;
-            let new_x = self.rect.x - 18;
-            let new_y = self.rect.y - 10;
-
-            self.rect.set_x(new_x);
-            self.rect.set_y(new_y);
-            self.rect.set_height(new_height);
-            self.rect.set_width(new_width);
+            self.scale.animate_to(1.0, timing.focus, timing.easing);
         }
     }
 
-    pub fn unselect(&mut self) {
+    /// Shrinks the tile back to its default size
+    pub fn unselect(&mut self, timing: &Timing) {
         if self.selected {
             self.selected = false;
-
-            // descale the image
-            let new_x = self.rect.x + 18_i32;
-            let new_y = self.rect.y + 10_i32;
-            self.rect.set_x(new_x);
-            self.rect.set_y(new_y);
-            self.rect.set_height(self.default_height);
-            self.rect.set_width(self.default_width);
+            self.scale.animate_to(0.0, timing.focus, timing.easing);
         }
     }
+
+    pub fn is_animating(&self) -> bool {
+        self.scale.is_active()
+    }
+
+    pub fn tick(&mut self, seconds: f32) {
+        self.scale.tick(seconds);
+    }
+
+    /// Resizes the tile to fit a shelf of a new height,
+    /// finishing any focus animation
+    pub fn resize(&mut self, height: u32) {
+        self.default_width = (height as f32 * self.aspect_ratio) as u32;
+        self.default_height = height;
+        self.scale.jump_to(self.scale.target());
+    }
+
+    /// How far a selected tile grows on each side, in proportion
+    /// to its size, 18x10 for a tile 160 high
+    fn grow(&self) -> (u32, u32) {
+        let grow_y = self.default_height / 16;
+        (grow_y * 9 / 5, grow_y)
+    }
+}
+
+/// The center of an image, cropped to the shape of the tile,
+/// for when the item did not have a variant of the right shape
+pub fn crop_to_fit(width: u32, height: u32, tile: Rect) -> Rect {
+    let (width, height) = (width.max(1), height.max(1));
+    // compare width / height ratios without dividing
+    let image_wider = width as u64 * tile.height() as u64 > height as u64 * tile.width() as u64;
+    if image_wider {
+        let cropped = (height as u64 * tile.width() as u64 / tile.height().max(1) as u64) as u32;
+        Rect::new(((width - cropped) / 2) as i32, 0, cropped.max(1), height)
+    } else {
+        let cropped = (width as u64 * tile.height() as u64 / tile.width().max(1) as u64) as u32;
+        Rect::new(0, ((height - cropped) / 2) as i32, width, cropped.max(1))
+    }
 }
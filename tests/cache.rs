@@ -0,0 +1,148 @@
+//! The on-disk image cache, see [`disney::service::cache`]
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::sleep;
+use std::time::Duration;
+
+use disney::service::cache::ImageCache;
+
+const BODY: &[u8] = b"an image, or close enough";
+
+/// An empty cache directory for the test
+fn cache_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("disney-cache-{}-{}", test, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn url(name: &str) -> String {
+    format!("https://cdn.example.com/{}.jpeg", name)
+}
+
+/// The bytes on disk for an image, with its header
+fn stored_size(url: &str) -> u64 {
+    (4 + 4 + url.len() + 8 + 8 + BODY.len()) as u64
+}
+
+fn files(dir: &Path, extension: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == extension))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Lets the clock move on, the last use is kept in
+/// modification times which may be coarse
+fn tick() {
+    sleep(Duration::from_millis(20));
+}
+
+#[test]
+fn images_are_kept_between_runs() {
+    let dir = cache_dir("kept");
+    let cache = ImageCache::open(&dir, 1 << 20).unwrap();
+    assert_eq!(cache.get(&url("a")), None);
+    cache.put(&url("a"), BODY).unwrap();
+    assert_eq!(cache.get(&url("a")).as_deref(), Some(BODY));
+
+    let cache = ImageCache::open(&dir, 1 << 20).unwrap();
+    assert_eq!(cache.get(&url("a")).as_deref(), Some(BODY));
+    assert_eq!(cache.get(&url("b")), None);
+
+    // written through a temp file, which is renamed into place
+    assert_eq!(files(&dir, "img").len(), 1);
+    assert!(files(&dir, "tmp").is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn the_least_recently_used_image_is_evicted() {
+    let dir = cache_dir("lru");
+    let cache = ImageCache::open(&dir, stored_size(&url("a")) * 2).unwrap();
+    cache.put(&url("a"), BODY).unwrap();
+    cache.put(&url("b"), BODY).unwrap();
+    cache.get(&url("a")).unwrap();
+    cache.put(&url("c"), BODY).unwrap();
+
+    assert_eq!(cache.get(&url("b")), None);
+    assert!(cache.get(&url("a")).is_some());
+    assert!(cache.get(&url("c")).is_some());
+    assert_eq!(files(&dir, "img").len(), 2);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn the_lru_order_survives_reopening() {
+    let dir = cache_dir("reopen");
+    let cache = ImageCache::open(&dir, 1 << 20).unwrap();
+    cache.put(&url("a"), BODY).unwrap();
+    tick();
+    cache.put(&url("b"), BODY).unwrap();
+    tick();
+    cache.get(&url("a")).unwrap();
+    drop(cache);
+
+    // the budget shrank, b was used longest ago
+    let cache = ImageCache::open(&dir, stored_size(&url("a"))).unwrap();
+    assert_eq!(cache.get(&url("b")), None);
+    assert_eq!(cache.get(&url("a")).as_deref(), Some(BODY));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn corrupt_images_are_removed() {
+    let dir = cache_dir("corrupt");
+    let cache = ImageCache::open(&dir, 1 << 20).unwrap();
+    cache.put(&url("flipped"), BODY).unwrap();
+    let flipped = files(&dir, "img").remove(0);
+    cache.put(&url("truncated"), BODY).unwrap();
+    let truncated = files(&dir, "img")
+        .into_iter()
+        .find(|path| *path != flipped)
+        .unwrap();
+
+    let mut data = fs::read(&flipped).unwrap();
+    *data.last_mut().unwrap() ^= 0xff;
+    fs::write(&flipped, data).unwrap();
+    let data = fs::read(&truncated).unwrap();
+    fs::write(&truncated, &data[..data.len() - 1]).unwrap();
+
+    assert_eq!(cache.get(&url("flipped")), None);
+    assert_eq!(cache.get(&url("truncated")), None);
+    assert!(files(&dir, "img").is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn an_image_for_another_url_is_a_miss() {
+    let dir = cache_dir("collision");
+    let cache = ImageCache::open(&dir, 1 << 20).unwrap();
+    cache.put(&url("b"), BODY).unwrap();
+    let b = files(&dir, "img").remove(0);
+    cache.put(&url("a"), b"another image").unwrap();
+    let a = files(&dir, "img")
+        .into_iter()
+        .find(|path| *path != b)
+        .unwrap();
+
+    // as if the two urls had the same file name
+    fs::copy(&a, &b).unwrap();
+    assert_eq!(cache.get(&url("b")), None);
+    assert_eq!(cache.get(&url("a")).as_deref(), Some(&b"another image"[..]));
+    // the file is intact, it is not removed as if it was corrupt
+    assert!(b.exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn interrupted_writes_are_cleaned_up() {
+    let dir = cache_dir("tmp");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("0123456789abcdef.img.0.tmp"), b"DIMG").unwrap();
+    ImageCache::open(&dir, 1 << 20).unwrap();
+    assert!(files(&dir, "tmp").is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
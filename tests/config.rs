@@ -0,0 +1,42 @@
+//! Where each setting comes from, see [`disney::config`]
+use disney::Config;
+
+const FILE: &str = "tests/fixtures/config/window.toml";
+
+#[test]
+fn flags_override_the_environment_which_overrides_the_file() {
+    // the only test that sets the environment, the others leave
+    // the window size alone so they can run alongside it
+    std::env::set_var("DISNEY_WINDOW_WIDTH", "1280");
+    std::env::set_var("DISNEY_WINDOW_HEIGHT", "720");
+    let config = Config::load_from(["disney", "--config", FILE, "--window-width", "800"]);
+    std::env::remove_var("DISNEY_WINDOW_WIDTH");
+    std::env::remove_var("DISNEY_WINDOW_HEIGHT");
+
+    let config = config.unwrap();
+    assert_eq!(config.window_width, 800);
+    assert_eq!(config.window_height, 720);
+    assert_eq!(config.concurrency, 4);
+}
+
+#[test]
+fn the_file_overrides_the_defaults() {
+    let config = Config::load_from(["disney", "--config", FILE]).unwrap();
+    assert_eq!(config.concurrency, 4);
+    assert_eq!(config.font_size, Config::default().font_size);
+}
+
+#[test]
+fn invalid_values_are_errors_rather_than_defaults() {
+    for args in [
+        &["--concurrency", "0"][..],
+        &["--concurrency", "many"],
+        &["--image-size", "wide"],
+        &["--animation-easing", "bouncy"],
+        &["--config", "tests/fixtures/config/invalid.toml"],
+        &["--config", "tests/fixtures/config/missing.toml"],
+    ] {
+        let config = Config::load_from(std::iter::once(&"disney").chain(args));
+        assert!(config.is_err(), "{:?}", args);
+    }
+}
@@ -0,0 +1,99 @@
+//! Checks tile images load most important first, see [`disney::service::image_queue`]
+use disney::event::TileRef;
+use disney::service::image_queue::{ImageQueue, TileImageRequest, Viewport};
+
+fn request(shelf: usize, tile: usize) -> TileImageRequest {
+    TileImageRequest {
+        img_url: format!("{}-{}.jpg", shelf, tile),
+        tiles: vec![TileRef { shelf, tile }],
+    }
+}
+
+/// Every shelf of 20 tiles, in document order
+fn queue(shelves: usize) -> ImageQueue {
+    let queue = ImageQueue::default();
+    for shelf in 0..shelves {
+        queue.push((0..20).map(|tile| request(shelf, tile)).collect());
+    }
+    queue
+}
+
+#[tokio::test]
+async fn without_a_viewport_images_load_in_order() {
+    let queue = queue(2);
+    assert_eq!(queue.next().await, request(0, 0));
+    assert_eq!(queue.next().await, request(0, 1));
+}
+
+#[tokio::test]
+async fn visible_tiles_load_first_then_their_neighbours() {
+    let queue = queue(12);
+    queue.set_viewport(Viewport {
+        focus: Some(TileRef { shelf: 5, tile: 8 }),
+        visible: vec![(5, 7..12), (6, 0..5)],
+    });
+
+    let mut visible = Vec::new();
+    for _ in 0..10 {
+        visible.push(queue.next().await.tiles[0]);
+    }
+    assert_eq!(visible[0], TileRef { shelf: 5, tile: 8 });
+    assert!(visible
+        .iter()
+        .all(|tile| (tile.shelf == 5 && (7..12).contains(&tile.tile))
+            || (tile.shelf == 6 && tile.tile < 5)));
+
+    // the next shelf down comes before shelves far away
+    let mut near = Vec::new();
+    for _ in 0..20 {
+        near.push(queue.next().await.tiles[0]);
+    }
+    assert!(near.iter().all(|tile| (4..=7).contains(&tile.shelf)));
+    assert!(near.contains(&TileRef { shelf: 5, tile: 12 }));
+}
+
+#[tokio::test]
+async fn moving_the_viewport_reorders_what_is_left() {
+    let queue = queue(12);
+    queue.set_viewport(Viewport {
+        focus: Some(TileRef { shelf: 0, tile: 0 }),
+        visible: vec![(0, 0..5)],
+    });
+    assert_eq!(queue.next().await.tiles[0].shelf, 0);
+
+    queue.set_viewport(Viewport {
+        focus: Some(TileRef { shelf: 10, tile: 0 }),
+        visible: vec![(10, 0..5)],
+    });
+    assert_eq!(queue.next().await, request(10, 0));
+    queue.done();
+    queue.done();
+    assert!(!queue.is_idle());
+}
+
+#[tokio::test]
+async fn an_image_on_several_shelves_loads_once() {
+    let queue = ImageQueue::default();
+    let shared = |shelf, tile| TileImageRequest {
+        img_url: "shared.jpg".to_string(),
+        tiles: vec![TileRef { shelf, tile }],
+    };
+    queue.push(vec![shared(0, 3)]);
+    queue.push(vec![request(1, 0), shared(1, 7)]);
+    queue.push(vec![shared(4, 1), shared(0, 3)]);
+
+    let first = queue.next().await;
+    assert_eq!(first.img_url, "shared.jpg");
+    assert_eq!(
+        first.tiles,
+        vec![
+            TileRef { shelf: 0, tile: 3 },
+            TileRef { shelf: 1, tile: 7 },
+            TileRef { shelf: 4, tile: 1 },
+        ]
+    );
+    assert_eq!(queue.next().await, request(1, 0));
+    queue.done();
+    queue.done();
+    assert!(queue.is_idle());
+}
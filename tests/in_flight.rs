@@ -0,0 +1,48 @@
+//! Checks concurrent image requests share one load, see [`disney::service::in_flight`]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use disney::service::in_flight::InFlight;
+
+#[tokio::test]
+async fn concurrent_requests_share_one_fetch() {
+    let in_flight = InFlight::default();
+    let fetches = AtomicUsize::new(0);
+    let fetch = || async {
+        fetches.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Ok(Bytes::from_static(b"tile"))
+    };
+
+    let (a, b, c) = tokio::join!(
+        in_flight.load("a.jpg", fetch),
+        in_flight.load("a.jpg", fetch),
+        in_flight.load("a.jpg", fetch),
+    );
+    assert_eq!(fetches.load(Ordering::SeqCst), 1);
+    for bytes in [a, b, c] {
+        assert_eq!(bytes.unwrap(), Bytes::from_static(b"tile"));
+    }
+    assert_eq!((in_flight.misses(), in_flight.hits()), (1, 2));
+
+    // once finished, the next request fetches again
+    in_flight.load("a.jpg", fetch).await.unwrap();
+    assert_eq!(fetches.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn a_failed_fetch_fails_every_request_for_it() {
+    let in_flight = InFlight::default();
+    let fetch = || async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        Err(anyhow!("404"))
+    };
+    let (a, b) = tokio::join!(
+        in_flight.load("missing.jpg", fetch),
+        in_flight.load("missing.jpg", fetch)
+    );
+    assert!(a.is_err());
+    assert!(b.unwrap_err().to_string().contains("404"));
+}
@@ -0,0 +1,10 @@
+//! Keys and buttons to actions, see [`disney::input::Bindings`]
+use disney::input::{Action, Bindings};
+use sdl2::keyboard::Keycode;
+
+#[test]
+fn escape_quits_and_backspace_goes_back() {
+    let bindings = Bindings::default();
+    assert_eq!(bindings.key_action(Keycode::Escape), Some(Action::Quit));
+    assert_eq!(bindings.key_action(Keycode::Backspace), Some(Action::Back));
+}
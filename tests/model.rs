@@ -0,0 +1,92 @@
+//! Parses the home screen json, see [`disney::model::home`]
+use std::path::Path;
+
+use anyhow::Result;
+use disney::model::home::{ItemKind, SetRef, SetRefWarning};
+use disney::{ContentSet, ContentSetItem};
+use serde_json::json;
+
+/// Reads a set ref from `tests/fixtures/set_refs`
+fn set_ref(name: &str) -> Result<SetRef> {
+    let path = Path::new("tests/fixtures/set_refs").join(format!("{}.json", name));
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+fn content_set(name: &str) -> (ContentSet, Vec<SetRefWarning>) {
+    set_ref(name)
+        .and_then(|set_ref| set_ref.content_set())
+        .expect("Unable to load set ref")
+}
+
+#[test]
+fn items_missing_their_type_or_text_are_kept() {
+    let item: ContentSetItem = serde_json::from_value(json!({
+        "image": {}
+    }))
+    .expect("Unable to parse item");
+    assert_eq!(item.kind(), ItemKind::Other);
+    assert_eq!(item.title(), None);
+
+    let item: ContentSetItem = serde_json::from_value(json!({
+        "type": "DmcSeries",
+        "text": { "title": { "full": { "series": { "default": { "content": "Andor" } } } } },
+        "image": {}
+    }))
+    .expect("Unable to parse item");
+    assert_eq!(item.kind(), ItemKind::Series);
+    assert_eq!(item.title().map(String::as_str), Some("Andor"));
+}
+
+#[test]
+fn an_item_with_only_other_titles_is_always_titled_the_same() {
+    let item: ContentSetItem = serde_json::from_value(json!({
+        "text": { "title": { "full": {
+            "season": { "default": { "content": "Season 1" } },
+            "episode": { "default": { "content": "Chapter 1" } },
+            "slug": { "default": { "content": "chapter-1" } }
+        } } },
+        "image": {}
+    }))
+    .expect("Unable to parse item");
+    assert_eq!(item.title().map(String::as_str), Some("Chapter 1"));
+}
+
+#[test]
+fn a_set_of_an_unknown_type_is_loaded_with_a_warning() {
+    let (set, warnings) = content_set("unknown_set_type");
+    assert_eq!(set.title(), "Editorial");
+    assert_eq!(set.set_type().map(String::as_str), Some("EditorialSet"));
+    assert_eq!(
+        warnings,
+        [SetRefWarning::UnknownSetType("EditorialSet".to_string())]
+    );
+}
+
+#[test]
+fn a_known_set_is_preferred_over_an_extra_set() {
+    let (set, warnings) = content_set("extra_set");
+    assert_eq!(set.title(), "Curated");
+    assert_eq!(
+        warnings,
+        [SetRefWarning::ExtraSet("EditorialSet".to_string())]
+    );
+}
+
+#[test]
+fn an_invalid_set_is_skipped_with_a_warning() {
+    let (set, warnings) = content_set("invalid_set");
+    assert_eq!(set.title(), "Curated");
+    assert!(matches!(
+        warnings.as_slice(),
+        [SetRefWarning::InvalidSet { set_type, .. }] if set_type == "BecauseYouSet"
+    ));
+}
+
+#[test]
+fn a_set_ref_without_a_set_is_an_error() {
+    // the set is not under data
+    assert!(set_ref("missing_data").is_err());
+    // the only set can't be read
+    let set_ref = set_ref("no_sets").unwrap();
+    assert!(set_ref.content_set().is_err());
+}
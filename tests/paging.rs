@@ -0,0 +1,62 @@
+//! Loading further pages of a content set, see [`disney::service::disney::set_page_path`]
+use std::path::PathBuf;
+
+use anyhow::Result;
+use disney::service::disney::set_page_path;
+use disney::ui::animation::Timing;
+use disney::ui::home_page::HomePage;
+use disney::ui::layout::Layout;
+use disney::{Config, ContentSource, DisneyService};
+
+const SET_ID: &str = "d0c43e18-1348-4b1c-9b3f-58eaa22fa5a7";
+
+#[test]
+fn pages_are_numbered_from_one() {
+    assert_eq!(
+        set_page_path("set", 0, 15),
+        "sets/set/pageSize/15/page/1.json"
+    );
+    assert_eq!(
+        set_page_path("set", 30, 15),
+        "sets/set/pageSize/15/page/3.json"
+    );
+}
+
+#[tokio::test]
+async fn pages_are_read_from_a_directory() -> Result<()> {
+    let source = ContentSource::Directory(PathBuf::from("tests/fixtures/paging"));
+    let disney = DisneyService::new(1, source);
+
+    let page = disney.load_set_page(SET_ID, 15, 15).await?;
+    let titles: Vec<_> = page.items_iter().filter_map(|item| item.title()).collect();
+    assert_eq!(titles, ["Into the Unknown: Making Frozen 2", "Becoming"]);
+    assert_eq!(page.hits(), 17);
+
+    // past the last page there is nothing to read
+    assert!(disney.load_set_page(SET_ID, 30, 15).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_failed_page_ends_paging() -> Result<()> {
+    let source = ContentSource::Directory(PathBuf::from("tests/fixtures/paging"));
+    let disney = DisneyService::new(1, source);
+
+    // two tiles of 17, the shelf asks for more as soon as it is selected
+    let page = disney.load_set_page(SET_ID, 15, 15).await?;
+    let config = Config::default();
+    let layout = Layout::new(&config, 1280, 720);
+    let mut home = HomePage::load(vec![page], layout, &config.image_size, Timing::instant());
+    let request = home.on_key_right().expect("No page requested");
+    assert_eq!(request.offset, 2);
+
+    // no further requests once the page fails, and no tiles are added
+    home.on_page_load(0, None);
+    home.on_key_left();
+    for _ in 0..3 {
+        assert!(home.on_key_right().is_none());
+    }
+    assert!(home.tile_rect(0, 1).is_some());
+    assert!(home.tile_rect(0, 2).is_none());
+    Ok(())
+}
@@ -0,0 +1,90 @@
+//! Which failures are retried and how long to wait, see [`disney::service::retry`]
+use std::time::Duration;
+
+use anyhow::{Error, Result};
+use disney::service::retry::{RetryPolicy, StatusError};
+use disney::Config;
+use reqwest::{Client, StatusCode};
+use tokio::net::TcpListener;
+
+fn policy() -> RetryPolicy {
+    RetryPolicy {
+        base_delay: Duration::from_millis(100),
+        max_delay: Duration::from_millis(1_000),
+        ..RetryPolicy::default()
+    }
+}
+
+#[test]
+fn the_default_policy_is_the_default_config() {
+    let policy = RetryPolicy::default();
+    let config = Config::default().retry_policy();
+    assert_eq!(policy.max_attempts, config.max_attempts);
+    assert_eq!(policy.base_delay, config.base_delay);
+    assert_eq!(policy.max_delay, config.max_delay);
+    assert_eq!(policy.connect_timeout, config.connect_timeout);
+    assert_eq!(policy.request_timeout, config.request_timeout);
+}
+
+#[test]
+fn backoff_doubles_up_to_the_max_delay() {
+    let policy = policy();
+    for attempt in 1..=10 {
+        let cap = (policy.base_delay * 2u32.pow(attempt - 1)).min(policy.max_delay);
+        for _ in 0..100 {
+            assert!(policy.backoff(attempt) <= cap, "attempt {}", attempt);
+        }
+    }
+    // no overflow however many attempts
+    assert!(policy.backoff(u32::MAX) <= policy.max_delay);
+}
+
+#[test]
+fn backoff_is_jittered() {
+    let policy = policy();
+    let delays: Vec<Duration> = (0..100).map(|_| policy.backoff(4)).collect();
+    let shortest = delays.iter().min().unwrap();
+    let longest = delays.iter().max().unwrap();
+    assert!(*longest - *shortest > Duration::from_millis(100));
+}
+
+#[test]
+fn server_errors_are_retried_and_client_errors_are_not() {
+    let policy = policy();
+    let status = |code: u16| Error::new(StatusError(StatusCode::from_u16(code).unwrap()));
+    for code in [500, 502, 503, 408, 429] {
+        assert!(policy.is_retryable(&status(code)), "{}", code);
+    }
+    for code in [400, 401, 403, 404] {
+        assert!(!policy.is_retryable(&status(code)), "{}", code);
+    }
+    // wherever the status is in the chain
+    assert!(policy.is_retryable(&status(503).context("Fetching home screen")));
+    assert!(!policy.is_retryable(&anyhow::anyhow!("Deserializing set")));
+}
+
+#[tokio::test]
+async fn timeouts_and_refused_connections_are_retried() -> Result<()> {
+    let policy = policy();
+
+    // accepts connections and never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let url = format!("http://{}/home.json", listener.local_addr()?);
+    let client = Client::builder()
+        .timeout(Duration::from_millis(100))
+        .build()?;
+    let timeout = client.get(&url).send().await.unwrap_err();
+    assert!(timeout.is_timeout());
+    assert!(policy.is_retryable(&Error::new(timeout)));
+
+    drop(listener);
+    let refused = client.get(&url).send().await.unwrap_err();
+    assert!(policy.is_retryable(&Error::new(refused)));
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_that_could_never_be_sent_are_not_retried() {
+    let error = Client::new().get("not a url").send().await.unwrap_err();
+    assert!(!policy().is_retryable(&Error::new(error)));
+}
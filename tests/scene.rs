@@ -0,0 +1,283 @@
+//! Checks where shelves and tiles end up on screen as the home page
+//! scrolls, without rendering, see [`disney::ui::scene`]
+use std::path::Path;
+
+use disney::event::TileRef;
+use disney::ui::animation::Timing;
+use disney::ui::backdrop::Backdrop;
+use disney::ui::headless::load_fixture;
+use disney::ui::home_page::{Focus, HomePage};
+use disney::ui::layout::Layout;
+use disney::Config;
+
+async fn fixture_home_page(timing: Timing) -> (HomePage, Layout) {
+    let content_sets = load_fixture(Path::new("assets"))
+        .await
+        .expect("Unable to load fixture");
+    let config = Config::default();
+    let layout = Layout::new(&config, 1280, 720);
+    let home = HomePage::load(content_sets, layout, &config.image_size, timing);
+    (home, layout)
+}
+
+#[tokio::test]
+async fn shelves_scroll_up_past_the_second_row() {
+    let (mut home, layout) = fixture_home_page(Timing::instant()).await;
+    let step = (layout.shelf_height + layout.shelf_padding) as i32;
+    let top = home.shelf_rect(0).unwrap().y();
+
+    // the first two rows do not scroll
+    home.on_key_down();
+    assert_eq!(home.shelf_rect(0).unwrap().y(), top);
+
+    home.on_key_down();
+    assert_eq!(home.shelf_rect(0).unwrap().y(), top - step);
+    assert_eq!(home.shelf_rect(2).unwrap().y(), top + step);
+
+    home.on_key_up();
+    assert_eq!(home.shelf_rect(0).unwrap().y(), top);
+}
+
+#[tokio::test]
+async fn tiles_scroll_left_with_the_selection() {
+    let (mut home, layout) = fixture_home_page(Timing::instant()).await;
+    let padding = layout.tile_padding as i32;
+    let width = home.tile_rect(0, 1).unwrap().width() as i32;
+    assert_eq!(home.tile_rect(0, 1).unwrap().x(), padding * 2 + width);
+
+    // the first tile is left half in view, then tiles move along a whole tile
+    home.on_key_right();
+    assert_eq!(home.tile_rect(0, 0).unwrap().x(), -width / 2);
+    home.on_key_right();
+    assert_eq!(home.tile_rect(0, 1).unwrap().x(), -width / 2);
+
+    // other shelves do not move
+    assert_eq!(home.tile_rect(1, 0).unwrap().x(), padding);
+}
+
+#[tokio::test]
+async fn the_selected_tile_grows_on_all_sides() {
+    let (mut home, _) = fixture_home_page(Timing::instant()).await;
+    let unselected = home.tile_rect(0, 1).unwrap();
+    home.on_key_right();
+    home.on_key_left();
+    assert_eq!(home.tile_rect(0, 1).unwrap(), unselected);
+
+    let selected = home.tile_rect(0, 0).unwrap();
+    home.on_key_down();
+    let unselected = home.tile_rect(0, 0).unwrap();
+    assert!(selected.width() > unselected.width());
+    assert!(selected.height() > unselected.height());
+    assert_eq!(selected.center(), unselected.center());
+}
+
+#[tokio::test]
+async fn scrolling_animates_to_the_same_place() {
+    let config = Config::default();
+    let (mut animated, _) = fixture_home_page(Timing::new(&config)).await;
+    let (mut instant, _) = fixture_home_page(Timing::instant()).await;
+    let top = animated.shelf_rect(0).unwrap().y();
+    for home in [&mut animated, &mut instant] {
+        home.on_key_down();
+        home.on_key_down();
+        home.on_key_right();
+    }
+
+    // part way there, and still moving
+    let scrolled = instant.shelf_rect(0).unwrap().y();
+    animated.tick(0.05);
+    let moving = animated.shelf_rect(0).unwrap().y();
+    assert!(animated.is_animating());
+    assert!(moving < top && moving > scrolled);
+
+    for _ in 0..60 {
+        animated.tick(1.0 / 60.0);
+    }
+    assert!(!animated.is_animating());
+    assert_eq!(animated.shelf_rect(0), instant.shelf_rect(0));
+    assert_eq!(animated.tile_rect(2, 1), instant.tile_rect(2, 1));
+}
+
+#[tokio::test]
+async fn the_hero_sits_above_the_first_shelf() {
+    let (home, layout) = fixture_home_page(Timing::instant()).await;
+    let hero = home.hero_rect().unwrap();
+    let shelf = home.shelf_rect(0).unwrap();
+    assert_eq!(hero.y(), layout.shelf_padding as i32);
+    assert_eq!(hero.width(), shelf.width() - layout.tile_padding * 2);
+    assert_eq!(shelf.y(), hero.bottom() + layout.shelf_padding as i32);
+}
+
+#[tokio::test]
+async fn up_from_the_first_shelf_focuses_the_hero() {
+    let (mut home, _) = fixture_home_page(Timing::instant()).await;
+    let first_tile = home.selected_item().unwrap().title().cloned();
+    home.on_key_up();
+    assert_eq!(home.focus(), Focus::Hero(0));
+
+    // left wraps around to the last program
+    home.on_key_right();
+    assert_eq!(home.focus(), Focus::Hero(1));
+    home.on_key_left();
+    home.on_key_left();
+    assert!(matches!(home.focus(), Focus::Hero(last) if last > 1));
+
+    home.on_key_down();
+    assert_eq!(home.focus(), Focus::Tile { row: 0, tile: 0 });
+    assert_eq!(home.selected_item().unwrap().title().cloned(), first_tile);
+}
+
+#[tokio::test]
+async fn the_hero_moves_on_unless_the_user_is_busy() {
+    let timing = Timing {
+        hero_interval: 5.0,
+        ..Timing::instant()
+    };
+    let (mut home, _) = fixture_home_page(timing).await;
+    home.on_key_up();
+    assert_eq!(home.time_to_next_tick(), Some(5.0));
+
+    home.tick(4.0);
+    assert_eq!(home.focus(), Focus::Hero(0));
+    home.tick(1.0);
+    assert_eq!(home.focus(), Focus::Hero(1));
+
+    // every key press starts the wait again
+    home.tick(4.0);
+    home.on_key_right();
+    home.tick(4.0);
+    assert_eq!(home.focus(), Focus::Hero(2));
+    home.tick(1.0);
+    assert_eq!(home.focus(), Focus::Hero(3));
+}
+
+#[tokio::test]
+async fn the_background_art_around_the_focus_is_requested_once() {
+    let (mut home, _) = fixture_home_page(Timing::instant()).await;
+    let focused = home.selected_item().and_then(Backdrop::image_url).cloned();
+    let first = home.take_image_requests();
+    assert!(focused.is_some());
+    assert_eq!(first.first(), focused.as_ref());
+    // the next tile along and the first row down are loaded ahead
+    assert!(first.len() > 1);
+
+    // already asked for, nothing new until the focus moves on
+    assert!(home.take_image_requests().is_empty());
+    home.on_key_right();
+    let next = home.take_image_requests();
+    assert!(next.iter().all(|url| !first.contains(url)));
+}
+
+#[tokio::test]
+async fn the_viewport_follows_the_focus() {
+    let (mut home, _) = fixture_home_page(Timing::instant()).await;
+    let viewport = home.viewport();
+    assert_eq!(viewport.focus, Some(TileRef { shelf: 0, tile: 0 }));
+    assert_eq!(viewport.visible[0].0, 0);
+    assert_eq!(viewport.visible[0].1.start, 0);
+
+    // shelves scroll off the top
+    let shelves = (0..)
+        .take_while(|row| home.shelf_rect(*row).is_some())
+        .count();
+    for _ in 0..shelves {
+        home.on_key_down();
+    }
+    let last = shelves - 1;
+    let viewport = home.viewport();
+    assert_eq!(
+        viewport.focus,
+        Some(TileRef {
+            shelf: last,
+            tile: 0
+        })
+    );
+    assert!(viewport.visible.iter().all(|(shelf, _)| *shelf > 0));
+    assert!(viewport.visible.iter().any(|(shelf, _)| *shelf == last));
+}
+
+#[tokio::test]
+async fn keys_scroll_from_where_the_pointer_moved_the_focus() {
+    // tall enough to point at a row that has not scrolled into place
+    let layout = Layout::new(&Config::default(), 1280, 1440);
+    let (mut pointed, _) = fixture_home_page(Timing::instant()).await;
+    let (mut keyed, _) = fixture_home_page(Timing::instant()).await;
+    pointed.on_resize(layout);
+    keyed.on_resize(layout);
+    let top = pointed.shelf_rect(0).unwrap().y();
+
+    // the lowest row on screen, before anything has scrolled
+    let row = (0..)
+        .take_while(|row| {
+            pointed
+                .tile_rect(*row, 2)
+                .is_some_and(|rect| rect.bottom() < layout.height as i32)
+        })
+        .last()
+        .unwrap();
+    assert!(row >= 2);
+    let center = pointed.tile_rect(row, 2).unwrap().center();
+    pointed.on_hover(center.x(), center.y());
+    assert_eq!(pointed.focus(), Focus::Tile { row, tile: 2 });
+    assert_eq!(pointed.shelf_rect(0).unwrap().y(), top);
+
+    // up goes to the row above, scrolled as if the keys had got there
+    pointed.on_key_up();
+    for _ in 0..row - 1 {
+        keyed.on_key_down();
+    }
+    assert!(pointed.shelf_rect(0).unwrap().y() <= top);
+    assert_eq!(pointed.shelf_rect(0), keyed.shelf_rect(0));
+
+    // left goes to the tile before, scrolled as if the keys had got there
+    let (mut pointed, _) = fixture_home_page(Timing::instant()).await;
+    let (mut keyed, _) = fixture_home_page(Timing::instant()).await;
+    let center = pointed.tile_rect(0, 2).unwrap().center();
+    pointed.on_hover(center.x(), center.y());
+    pointed.on_key_left();
+    for _ in 0..2 {
+        keyed.on_key_right();
+    }
+    keyed.on_key_left();
+    assert_eq!(pointed.focus(), Focus::Tile { row: 0, tile: 1 });
+    assert_eq!(pointed.focus(), keyed.focus());
+    let width = pointed.tile_rect(0, 0).unwrap().width() as i32;
+    assert_eq!(pointed.tile_rect(0, 0).unwrap().x(), -width / 2);
+    assert_eq!(pointed.tile_rect(0, 0), keyed.tile_rect(0, 0));
+}
+
+#[tokio::test]
+async fn back_returns_to_the_top_before_quitting() {
+    let (mut home, _) = fixture_home_page(Timing::instant()).await;
+    let top = home.shelf_rect(0).unwrap().y();
+    home.on_key_down();
+    home.on_key_down();
+    home.on_key_right();
+    assert_eq!(home.focus(), Focus::Tile { row: 2, tile: 1 });
+
+    assert!(home.on_back());
+    assert_eq!(home.focus(), Focus::Hero(0));
+    assert_eq!(home.shelf_rect(0).unwrap().y(), top);
+    // already at the top, the app may quit
+    assert!(!home.on_back());
+
+    // the shelf is where it was left
+    home.on_key_down();
+    home.on_key_down();
+    home.on_key_down();
+    assert_eq!(home.focus(), Focus::Tile { row: 2, tile: 1 });
+}
+
+#[tokio::test]
+async fn standard_tiles_are_the_shape_of_the_image_size() {
+    let content_sets = load_fixture(Path::new("assets"))
+        .await
+        .expect("Unable to load fixture");
+    let layout = Layout::new(&Config::default(), 1280, 720);
+    for (image_size, ratio) in [("1.78", 1.78), ("1.33", 1.33)] {
+        let home = HomePage::load(content_sets.clone(), layout, image_size, Timing::instant());
+        let rect = home.tile_rect(0, 1).unwrap();
+        let shape = rect.width() as f32 / rect.height() as f32;
+        assert!((shape - ratio).abs() < 0.02, "{} is {}", image_size, shape);
+    }
+}
@@ -0,0 +1,181 @@
+//! Renders the ui offscreen and compares each frame against
+//! its golden PNG in `tests/golden`, see [`disney::ui::headless`]
+use std::path::Path;
+
+use anyhow::Result;
+use bytes::Bytes;
+use disney::input::{Action, Direction, Pointer};
+use disney::ui::headless::{load_fixture, Screenshot};
+use disney::Config;
+use sdl2::keyboard::Keycode;
+
+async fn fixture_screenshot() -> Screenshot {
+    let content_sets = load_fixture(Path::new("assets"))
+        .await
+        .expect("Unable to load fixture");
+    let config = Config {
+        window_width: 1280,
+        window_height: 720,
+        ..Config::default()
+    };
+    Screenshot::new(config, content_sets)
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.render()?.assert_golden("home_page")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_navigated() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Down, Keycode::Down, Keycode::Right, Keycode::Right]);
+    screenshot.render()?.assert_golden("home_page_navigated")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn details_page() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Right, Keycode::Return]);
+    screenshot.render()?.assert_golden("details_page")
+}
+
+#[tokio::test]
+async fn details_page_closed() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Right, Keycode::Return, Keycode::Escape]);
+    let closed = screenshot.render()?;
+
+    let mut never_opened = fixture_screenshot().await;
+    never_opened.press(Keycode::Right);
+    assert_eq!(closed.diff(&never_opened.render()?, 0)?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_4k() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.resize(3840, 2160);
+    screenshot.render()?.assert_golden("home_page_4k")
+}
+
+#[tokio::test]
+async fn home_page_resized() -> Result<()> {
+    let keys = [
+        Keycode::Down,
+        Keycode::Down,
+        Keycode::Right,
+        Keycode::Right,
+        Keycode::Right,
+    ];
+    let mut resized = fixture_screenshot().await;
+    resized.press_all(&keys);
+    resized.resize(1920, 1080);
+
+    // resizing keeps the selection, laid out as if it had always been this size
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.resize(1920, 1080);
+    screenshot.press_all(&keys);
+    assert_eq!(resized.render()?.diff(&screenshot.render()?, 0)?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn missing_artwork() -> Result<()> {
+    // items with no artwork, or only a background, still get a tile
+    let content_sets = load_fixture(Path::new("tests/fixtures/missing_artwork")).await?;
+    let config = Config {
+        window_width: 1280,
+        window_height: 720,
+        ..Config::default()
+    };
+    let mut screenshot = Screenshot::new(config, content_sets);
+    screenshot.render()?.assert_golden("missing_artwork")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn corrupt_artwork() -> Result<()> {
+    // a tile whose image fails to decode shows its title instead
+    let mut screenshot = fixture_screenshot().await;
+    assert!(screenshot.load_tile_image(0, 1, Bytes::from_static(b"not a jpeg")));
+    screenshot.render()?.assert_golden("corrupt_artwork")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_paged() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::PageDown, Keycode::PageDown, Keycode::PageUp]);
+    screenshot.render()?.assert_golden("home_page_paged")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_hovered() -> Result<()> {
+    // the second tile of the second shelf, the selection moves without scrolling
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.point(Pointer::Hover { x: 500, y: 300 });
+    screenshot.render()?.assert_golden("home_page_hovered")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_flung() -> Result<()> {
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.point(Pointer::Drag {
+        x: 600,
+        y: 120,
+        dx: -40.0,
+        dy: 0.0,
+    });
+    screenshot.point(Pointer::Fling {
+        x: 560,
+        y: 120,
+        velocity_x: -2000.0,
+    });
+    screenshot.render()?.assert_golden("home_page_flung")
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_mid_scroll() -> Result<()> {
+    // part way through scrolling down a row and over a tile,
+    // with the focus moving from one tile to the next
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Down, Keycode::Right]);
+    screenshot.begin(Action::Navigate(Direction::Down));
+    screenshot.begin(Action::Navigate(Direction::Right));
+    screenshot.wait(0.1);
+    screenshot.render()?.assert_golden("home_page_mid_scroll")
+}
+
+#[tokio::test]
+async fn home_page_interrupted() -> Result<()> {
+    // a key pressed part way through an animation
+    // ends up in the same place as one pressed after
+    let mut interrupted = fixture_screenshot().await;
+    interrupted.begin(Action::Navigate(Direction::Right));
+    interrupted.wait(0.05);
+    interrupted.press_all(&[Keycode::Right, Keycode::Down]);
+
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Right, Keycode::Right, Keycode::Down]);
+    assert_eq!(interrupted.render()?.diff(&screenshot.render()?, 0)?, 0);
+    Ok(())
+}
+
+#[tokio::test]
+#[ignore = "no golden yet, see tests/golden/README.md"]
+async fn home_page_hero() -> Result<()> {
+    // the hero focused, on its second program
+    let mut screenshot = fixture_screenshot().await;
+    screenshot.press_all(&[Keycode::Up, Keycode::Right]);
+    screenshot.render()?.assert_golden("home_page_hero")
+}
@@ -0,0 +1,155 @@
+//! The same home screen from each content source,
+//! see [`disney::service::source::ContentSource`]
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use disney::{ContentSet, ContentSource, DisneyService};
+use futures::{StreamExt, TryStreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Serves the files of a directory over http, as the cdn would,
+/// returning the base url
+async fn replay(dir: &'static str) -> Result<String> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let base_url = format!("http://{}", listener.local_addr()?);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(respond(stream, Path::new(dir)));
+        }
+    });
+    Ok(base_url)
+}
+
+async fn respond(mut stream: TcpStream, dir: &Path) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split(' ').nth(1).unwrap_or("/");
+    let (status, body) = match tokio::fs::read(dir.join(path.trim_start_matches('/'))).await {
+        Ok(body) => ("200 OK", body),
+        Err(_) => ("404 Not Found", Vec::new()),
+    };
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(&body).await?;
+    Ok(())
+}
+
+/// The title of each shelf with the titles of its items
+async fn shelves(source: ContentSource) -> Result<Vec<(String, Vec<String>)>> {
+    let disney = DisneyService::new(4, source);
+    let content_sets: Vec<ContentSet> = disney
+        .stream_home_content_sets()
+        .await?
+        .try_collect()
+        .await?;
+    Ok(content_sets
+        .iter()
+        .map(|content_set| {
+            let items = content_set
+                .items_iter()
+                .map(|item| item.title().cloned().unwrap_or_default())
+                .collect();
+            (content_set.title().clone(), items)
+        })
+        .collect())
+}
+
+#[tokio::test]
+async fn every_shelf_of_the_fixture_loads() -> Result<()> {
+    let shelves = shelves(ContentSource::Directory(PathBuf::from("assets"))).await?;
+    assert_eq!(shelves.len(), 13);
+    for (title, items) in &shelves {
+        assert!(!items.is_empty(), "{} is empty", title);
+    }
+    // set refs keep the title from the home screen
+    assert!(shelves
+        .iter()
+        .any(|(title, _)| title == "Because You Watched Gordon Ramsey"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn directory_and_network_sources_yield_the_same_shelves() -> Result<()> {
+    let base_url = replay("assets").await?;
+    let network = shelves(ContentSource::network(&base_url)).await?;
+    let directory = shelves(ContentSource::Directory(PathBuf::from("assets"))).await?;
+    assert_eq!(network, directory);
+    Ok(())
+}
+
+#[tokio::test]
+async fn only_sources_that_serve_pages_keep_paging() -> Result<()> {
+    let base_url = replay("assets").await?;
+    let network = DisneyService::new(4, ContentSource::network(&base_url));
+    let directory = DisneyService::new(4, ContentSource::Directory(PathBuf::from("assets")));
+    assert!(!network.serves_pages());
+    assert!(directory.serves_pages());
+
+    let whole = |content_sets: &[ContentSet]| {
+        content_sets
+            .iter()
+            .all(|content_set| content_set.hits() == content_set.items_iter().count())
+    };
+    let over_network: Vec<ContentSet> = network
+        .stream_home_content_sets()
+        .await?
+        .try_collect()
+        .await?;
+    let from_directory: Vec<ContentSet> = directory
+        .stream_home_content_sets()
+        .await?
+        .try_collect()
+        .await?;
+    assert!(whole(&over_network));
+    assert!(!whole(&from_directory));
+
+    // pages are not even asked for
+    assert!(network.load_set_page("set", 15, 15).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_images_are_yielded_for_those_waiting_on_every_image() -> Result<()> {
+    let base_url = replay("assets").await?;
+    let disney = DisneyService::new(2, ContentSource::network(&base_url));
+    let found = format!("{}/home.json", base_url);
+    let missing = format!("{}/missing.jpg", base_url);
+    let urls = vec![found.clone(), missing.clone()];
+
+    let mut results: Vec<_> = disney.stream_image_results(urls.clone()).collect().await;
+    results.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(results[0].0, found);
+    assert!(results[0].1.is_some());
+    assert_eq!(results[1], (missing, None));
+
+    // the plain stream leaves them out
+    let images: Vec<_> = disney.stream_images(urls).collect().await;
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].0, found);
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_missing_home_screen_fails_over_either_source() -> Result<()> {
+    let base_url = replay("tests").await?;
+    let network = DisneyService::new(1, ContentSource::network(&base_url));
+    let directory = DisneyService::new(1, ContentSource::Directory(PathBuf::from("tests")));
+    for disney in [network, directory] {
+        // there is no home screen here at all
+        assert!(disney.stream_home_content_sets().await.is_err());
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+//! Reads containers out of the home screen as it arrives in chunks,
+//! see [`disney::model::stream::ContainerParser`]
+use anyhow::Result;
+use disney::model::stream::ContainerParser;
+
+/// A home screen with a title that looks like json, so a
+/// parser that loses track of strings ends containers early
+const HOME: &str = r#"{"data": {"StandardCollection": {"text": {"a \"containers\": [": "}"},
+    "containers": [
+        {"style": "editorial", "set": {"text": {"title": {"full": {"set": {"default": {"content": "One"}}}}}}},
+        {"set": {"text": {"title": {"full": {"set": {"default": {"content": "Two \"{quoted}\" [x] \\"}}}}}}}
+    ], "after": {"containers": []}}}}"#;
+
+/// The titles of the containers read from the chunks,
+/// in order, with an error for each that failed
+fn titles(chunks: &[&[u8]]) -> Vec<Result<String>> {
+    let mut parser = ContainerParser::new();
+    for chunk in chunks {
+        parser.feed(chunk);
+    }
+    parser.finish();
+    std::iter::from_fn(|| parser.next_container())
+        .map(|container| container.map(|c| c.content_set().title().clone()))
+        .collect()
+}
+
+fn ok(titles: Vec<Result<String>>) -> Vec<String> {
+    titles
+        .into_iter()
+        .map(|title| title.expect("Unable to read container"))
+        .collect()
+}
+
+#[test]
+fn containers_are_read_whole() {
+    let expected = vec!["One".to_string(), "Two \"{quoted}\" [x] \\".to_string()];
+    assert_eq!(ok(titles(&[HOME.as_bytes()])), expected);
+}
+
+#[test]
+fn containers_are_read_across_chunks() {
+    let home = HOME.as_bytes();
+    let whole = ok(titles(&[home]));
+
+    // split everywhere, including inside strings and between
+    // a backslash and the character it escapes
+    for split in 0..home.len() {
+        let (first, rest) = home.split_at(split);
+        assert_eq!(ok(titles(&[first, rest])), whole, "split at {}", split);
+    }
+    let bytes: Vec<&[u8]> = home.chunks(1).collect();
+    assert_eq!(ok(titles(&bytes)), whole);
+}
+
+#[test]
+fn a_truncated_home_screen_is_an_error() {
+    let home = HOME.as_bytes();
+    let second = HOME.find(r#"{"set": {"text""#).unwrap();
+
+    // the first container is read, then the end is an error
+    let read = titles(&[&home[..second + 10]]);
+    assert_eq!(read.len(), 2);
+    assert_eq!(read[0].as_ref().unwrap(), "One");
+    assert!(read[1].is_err());
+
+    let read = titles(&[&home[..20]]);
+    assert_eq!(read.len(), 1);
+    assert!(read[0].is_err());
+}
+
+#[test]
+fn a_malformed_container_does_not_stop_the_rest() {
+    let home = r#"{"containers": [
+        {"style": 7, "set": {"text": "not a title"}},
+        {"set": {"text": {"title": {"full": {"set": {"default": {"content": "Valid"}}}}}}}
+    ]}"#;
+    let read = titles(&[home.as_bytes()]);
+    assert_eq!(read.len(), 2);
+    assert!(read[0].is_err());
+    assert_eq!(read[1].as_ref().unwrap(), "Valid");
+}
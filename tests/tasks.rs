@@ -0,0 +1,44 @@
+//! Checks background loading stops when its owner says so, see [`disney::tasks`]
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use disney::tasks::Tasks;
+
+/// A task that would take a minute, and notes if it finished
+fn slow_task(finished: &Arc<AtomicBool>) -> impl std::future::Future<Output = ()> {
+    let finished = Arc::clone(finished);
+    async move {
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        finished.store(true, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test]
+async fn shutdown_stops_tasks_straight_away() {
+    let finished = Arc::new(AtomicBool::new(false));
+    let mut tasks = Tasks::new();
+    tasks.spawn(slow_task(&finished));
+    tasks.spawn(slow_task(&finished));
+    assert_eq!(tasks.len(), 2);
+
+    let start = Instant::now();
+    assert!(tasks.shutdown(Duration::from_secs(5)).await);
+    assert!(start.elapsed() < Duration::from_secs(1));
+    assert!(!finished.load(Ordering::SeqCst));
+}
+
+#[tokio::test]
+async fn cancelled_groups_take_new_tasks() {
+    let mut tasks = Tasks::new();
+    tasks.spawn(slow_task(&Arc::new(AtomicBool::new(false))));
+    tasks.cancel();
+    assert!(tasks.is_empty());
+
+    let finished = Arc::new(AtomicBool::new(false));
+    let done = Arc::clone(&finished);
+    tasks.spawn(async move { done.store(true, Ordering::SeqCst) });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(finished.load(Ordering::SeqCst));
+    assert!(tasks.is_empty());
+}